@@ -1,50 +1,89 @@
+use crate::capture::Capture;
+use crate::connection::{ConnectionStatus, Credentials};
+use crate::control::{self, ControlConfig, ControlServer};
+use crate::discovery;
+use crate::error::Report;
 use crate::event::Event;
+use crate::instance::Instances;
 use crate::interop::spawn_interop_thread;
-use crate::ui::account::Account;
-use crate::ui::friendlist::Friendlist;
-use crate::ui::game::{Game, SearchState};
+use crate::repaint::{self, Repainter, Wake};
+use crate::ui::game::SearchState;
+use crate::ui::logs::LogPanel;
+use crate::ui::toasts::Toasts;
 use eframe::{egui, epi};
-use kassadin::client::LCU;
 
 use crate::TextureManager;
-use std::time::Duration;
+use std::sync::Arc;
+use std::time::Instant;
 
 pub struct App {
-    game: Game,
-    account: Account,
-    friendlist: Friendlist,
-    instances: Vec<Instance>,
-    lcu: kassadin::client::LCU,
-    sender: crossbeam::channel::Sender<Event>,
+    instances: Instances,
+    toasts: Toasts,
+    logs: LogPanel,
+    /// `None` unless enabled in the control config
+    control: Option<ControlServer>,
     receiver: crossbeam::channel::Receiver<Event>,
+    /// set up once there is a window to wake
+    repainter: Option<Repainter>,
     textures: TextureManager,
     show_window: bool,
 }
 
 impl Default for App {
     fn default() -> Self {
+        Self::new(Credentials::Lockfile, Capture::None)
+    }
+}
+
+impl App {
+    /// Build the app against a specific client, e.g. one from `crate::mock`,
+    /// optionally recording or replaying its websocket sessions.
+    /// With `Credentials::Lockfile` every running client gets its own instance.
+    pub fn new(credentials: Credentials, capture: Capture) -> Self {
         let (app_lol_send, app_lol_recv) = crossbeam::channel::unbounded();
         let (lol_app_send, lol_app_recv) = crossbeam::channel::unbounded();
 
-        let lcu = LCU::new();
+        let mut toasts = Toasts::default();
+        let control_config = ControlConfig::load();
+        let control = if control_config.enabled {
+            match control::spawn(&control_config, lol_app_send.clone()) {
+                Ok(control) => Some(control),
+                Err(e) => {
+                    toasts.push(Report::new(e).wrap_err("Start control api"));
+                    None
+                }
+            }
+        } else {
+            None
+        };
 
-        spawn_interop_thread(lol_app_send, app_lol_recv, &lcu);
+        let replay = matches!(capture, Capture::Replay { .. });
+        spawn_interop_thread(lol_app_send.clone(), app_lol_recv, capture);
+
+        let mut instances = Instances::new(app_lol_send, lol_app_send.clone());
+        match credentials {
+            Credentials::Lockfile if !replay => {
+                let clients = discovery::running_clients();
+                instances.sync(clients.clone(), &mut toasts);
+                discovery::watch(lol_app_send, clients);
+            }
+            credentials => {
+                instances.add("Client", None, None, credentials);
+            }
+        }
 
         Self {
-            game: Default::default(),
-            sender: app_lol_send,
             receiver: lol_app_recv,
-            friendlist: Friendlist::new(&lcu),
-            account: Default::default(),
-            lcu,
-            instances: vec![],
+            instances,
+            toasts,
+            logs: Default::default(),
+            control,
+            repainter: None,
             textures: Default::default(),
             show_window: true,
         }
     }
-}
 
-impl App {
     fn load_images(&mut self, frame: &epi::Frame) {
         self.textures.add_image(
             "top",
@@ -134,33 +173,83 @@ impl App {
 impl epi::App for App {
     fn update(&mut self, ctx: &egui::CtxRef, frame: &epi::Frame) {
         let Self {
-            game,
-            friendlist,
             instances,
-            lcu,
-            sender,
+            toasts,
+            logs,
+            control,
             receiver,
-            account,
+            repainter,
             textures,
             show_window,
         } = self;
 
-        crate::interop::match_events(receiver, sender, ctx, frame, lcu, game, account, friendlist);
+        crate::interop::match_events(receiver, ctx, frame, instances, toasts);
+        if let Some(repainter) = repainter {
+            // every instance, auto accept has to fire for the ones not shown too
+            let now = Instant::now();
+            let next = instances
+                .list
+                .iter()
+                .map(|instance| instance.game.next_repaint(now))
+                .fold(None, repaint::earliest);
+            if let Some(at) = next {
+                repainter.repaint_at(at);
+            }
+        }
+        if let Some(control) = control {
+            control.publish(control::snapshot(instances));
+        }
 
         egui::SidePanel::left("left_panel")
             .width_range(260.0..=260.0)
             .show(ctx, |ui| {
-                // TODO: Each instance should have a place here
+                instances.ui(ui);
+                ui.separator();
+                ui.horizontal(|ui| {
+                    if ui.small_button("Log").clicked() {
+                        logs.open = !logs.open;
+                    }
+                    if let Some(control) = control {
+                        ui.label(
+                            egui::RichText::new(format!("API :{}", control.port))
+                                .text_style(egui::TextStyle::Small),
+                        )
+                        .on_hover_text(format!("token {}", control.token));
+                    }
+                    if !toasts.history.is_empty() {
+                        let errors = format!("Errors ({})", toasts.history.len());
+                        if ui.small_button(errors).clicked() {
+                            toasts.show_history = !toasts.show_history;
+                        }
+                    }
+                });
             });
+        toasts.ui(ctx);
+        logs.ui(ctx);
+
+        let instance = match instances.selected_mut() {
+            Some(instance) if instance.connection == ConnectionStatus::Connected => instance,
+            instance => {
+                let status = instance.map(|instance| instance.connection.clone());
+                egui::CentralPanel::default().show(ctx, |ui| {
+                    crate::ui::waiting::ui(ui, status);
+                });
+                return;
+            }
+        };
+        let game = &mut instance.game;
+        let commands = &mut instance.commands;
+        let friendlist = &mut instance.friendlist;
+        let chat = &mut instance.chat;
 
         egui::CentralPanel::default()
             .show(ctx, |ui| {
-            game.ui(ui, lcu, textures, friendlist);
+            game.ui(ui, commands, textures, friendlist, chat);
 
-            if game.search_sate == SearchState::Searching {
+            if game.flow.state() == SearchState::Searching {
                 ui.horizontal(|ui| {
-                    if let Some(timer) = game.queue_timer {
-                        ui.label(format!("{}", timer));
+                    if let Some(in_queue) = game.time_in_queue(Instant::now()) {
+                        ui.label(format!("{}", in_queue.as_secs()));
                     }
                     if let Some(estimated) = game.estimated_queue_time {
                         ui.label(format!("{}", estimated));
@@ -172,8 +261,9 @@ impl epi::App for App {
         egui::SidePanel::right("right_panel")
             .width_range(260.0..=260.0)
             .show(ctx, |ui| {
-                friendlist.ui(ui);
+                friendlist.ui(ui, chat, commands);
             });
+        chat.ui_direct(ctx, commands);
 
         egui::Window::new("Window")
             .drag_bounds(ctx.used_rect())
@@ -195,28 +285,23 @@ impl epi::App for App {
         frame.set_window_size(egui::Vec2::new(1280.0, 720.0));
         self.configure_fonts(ctx);
         self.load_images(frame);
-        let lock = frame.0.lock().unwrap();
-        let repaint_signal = lock.repaint_signal.clone();
-        std::thread::spawn(move || loop {
-            std::thread::sleep(Duration::from_millis(100));
-            repaint_signal.request_repaint();
-        });
-        self.sender.send(Event::CreateSocket(0)).unwrap();
+
+        let repaint_signal = frame.0.lock().unwrap().repaint_signal.clone();
+        let wake: Wake = Arc::new(move || repaint_signal.request_repaint());
+        self.receiver = repaint::relay(self.receiver.clone(), wake.clone());
+        self.repainter = Some(Repainter::spawn(wake));
     }
 
     fn save(&mut self, _storage: &mut dyn epi::Storage) {
-        self.game.save();
-        self.friendlist.save();
+        for instance in &self.instances.list {
+            if let Err(e) = instance.save() {
+                self.toasts
+                    .push(e.wrap_err(format!("Save settings of {}", instance.name)));
+            }
+        }
     }
 
     fn name(&self) -> &str {
         "Clowncher"
     }
 }
-
-pub struct Instance {
-    pub id: i32,
-    pub game: Game,
-    pub account: Account,
-    pub lcu: LCU,
-}
@@ -0,0 +1,183 @@
+//! Drives the client from the shell, e.g. `clowncher-cli lobby solo middle top && clowncher-cli queue start`.
+
+use clowncher::command::{self, Command};
+use clowncher::connection::{self, Credentials};
+use clowncher::discovery;
+use clowncher::event::Event;
+use clowncher::lcu;
+use clowncher::queues;
+use clowncher::router::Router;
+use kassadin::types::lcu::consts::Position;
+use kassadin::types::lcu::lobby::PositionPreference;
+use std::sync::atomic::AtomicBool;
+use std::sync::Arc;
+
+const USAGE: &str =
+    "usage: clowncher-cli [--pid <pid> | --port <port> --password <password>] <command>
+
+commands:
+  clients                          list running clients
+  lobby <queue> [first] [second]   create a lobby, queue is solo, draft, flex, blind, clash, aram
+                                   or the client's queue id
+  leave                            leave the lobby
+  positions <first> [second]       set positions: top, jungle, middle, bottom, utility or fill
+  queue start|stop                 start or stop searching
+  accept | decline                 answer the ready check
+  invite <summoner id>             invite someone into the lobby
+  kick <summoner id>               kick someone from the lobby
+  dodge                            leave champ select
+  watch                            print client events as they arrive";
+
+fn main() {
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+    let (options, args) = split_options(&args);
+
+    let result = match args.first().map(String::as_str) {
+        Some("clients") => clients(),
+        Some("watch") => credentials(&options).map(watch),
+        Some(_) => parse_command(&args).and_then(|command| run(credentials(&options)?, command)),
+        None => Err(USAGE.to_string()),
+    };
+
+    if let Err(e) = result {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    }
+}
+
+/// `--flag value` pairs in front of the command, and everything after them.
+fn split_options(args: &[String]) -> (Vec<(String, String)>, Vec<String>) {
+    let mut options = vec![];
+    let mut rest = args;
+    while let [flag, value, tail @ ..] = rest {
+        if !flag.starts_with("--") {
+            break;
+        }
+        options.push((flag.clone(), value.clone()));
+        rest = tail;
+    }
+    (options, rest.to_vec())
+}
+
+fn option<'a>(options: &'a [(String, String)], flag: &str) -> Option<&'a str> {
+    options
+        .iter()
+        .find(|(name, _)| name == flag)
+        .map(|(_, value)| value.as_str())
+}
+
+fn credentials(options: &[(String, String)]) -> Result<Credentials, String> {
+    if let Some(pid) = option(options, "--pid") {
+        let pid = pid.parse::<u32>().map_err(|e| format!("bad pid: {}", e))?;
+        let client = discovery::running_clients()
+            .into_iter()
+            .find(|client| client.pid == pid)
+            .ok_or(format!("no client with pid {}", pid))?;
+        return Ok(Credentials::Fixed(client.lcu()));
+    }
+    if let Some(port) = option(options, "--port") {
+        let port = port
+            .parse::<u16>()
+            .map_err(|e| format!("bad port: {}", e))?;
+        let password = option(options, "--password").ok_or("--port needs --password")?;
+        return Ok(Credentials::Fixed(lcu::from_credentials(port, password)));
+    }
+    Ok(Credentials::Lockfile)
+}
+
+fn parse_command(args: &[String]) -> Result<Command, String> {
+    let args = args.iter().map(String::as_str).collect::<Vec<_>>();
+    let command = match args.as_slice() {
+        ["lobby", queue, positions @ ..] => {
+            Command::JoinLobby(parse_queue(queue)?, parse_positions(positions)?)
+        }
+        ["leave"] => Command::LeaveLobby,
+        ["positions", positions @ ..] if !positions.is_empty() => {
+            Command::SetRoles(parse_positions(positions)?)
+        }
+        ["queue", "start"] => Command::StartQueue,
+        ["queue", "stop"] => Command::StopQueue,
+        ["accept"] => Command::Accept,
+        ["decline"] => Command::Decline,
+        ["invite", id] => Command::Invite(parse_summoner_id(id)?),
+        ["kick", id] => Command::Kick(parse_summoner_id(id)?),
+        ["dodge"] => Command::Dodge,
+        _ => return Err(USAGE.to_string()),
+    };
+    Ok(command)
+}
+
+fn parse_queue(queue: &str) -> Result<i64, String> {
+    let known = queues::KNOWN
+        .iter()
+        .find(|(_, _, name)| name.eq_ignore_ascii_case(queue));
+    match known {
+        Some((_, queue_id, _)) => Ok(*queue_id),
+        None => queue
+            .parse()
+            .map_err(|_| format!("unknown queue: {}", queue)),
+    }
+}
+
+fn parse_positions(positions: &[&str]) -> Result<PositionPreference, String> {
+    if positions.len() > 2 {
+        return Err("at most two positions".to_string());
+    }
+    let position = |i: usize| positions.get(i).map(|p| parse_position(p)).transpose();
+    Ok(PositionPreference {
+        first_preference: position(0)?,
+        second_preference: position(1)?,
+    })
+}
+
+fn parse_position(position: &str) -> Result<Position, String> {
+    match position {
+        "top" => Ok(Position::TOP),
+        "jungle" => Ok(Position::JUNGLE),
+        "middle" | "mid" => Ok(Position::MIDDLE),
+        "bottom" | "bot" => Ok(Position::BOTTOM),
+        "utility" | "support" => Ok(Position::UTILITY),
+        "fill" => Ok(Position::FILL),
+        _ => Err(format!("unknown position: {}", position)),
+    }
+}
+
+fn parse_summoner_id(id: &str) -> Result<i64, String> {
+    id.parse()
+        .map_err(|e| format!("bad summoner id {}: {}", id, e))
+}
+
+fn clients() -> Result<(), String> {
+    for client in discovery::running_clients() {
+        println!("{}\t{}\t{}", client.pid, client.port, client.label);
+    }
+    Ok(())
+}
+
+fn run(credentials: Credentials, command: Command) -> Result<(), String> {
+    let lcu = credentials.read().ok_or("no client running")?;
+    command::run_blocking(&lcu, &command)
+        .map(|_| ())
+        .map_err(|e| format!("{:?}", e))
+}
+
+/// Prints every event until the client goes away for good or the process is killed.
+fn watch(credentials: Credentials) {
+    let (send, events) = crossbeam::channel::unbounded();
+    let stop = Arc::new(AtomicBool::new(false));
+    let router = Arc::new(Router::league());
+    std::thread::spawn(move || {
+        connection::run(0, credentials, router, None, send, stop);
+    });
+
+    for event in events {
+        match event {
+            Event::LeagueEvent(_, event) => {
+                println!("{} {:?} {:?}", event.uri, event.event_type, event.kind)
+            }
+            Event::Connection(_, status) => eprintln!("{}", status.label()),
+            Event::Error(e) => eprintln!("{:?}", e),
+            _ => {}
+        }
+    }
+}
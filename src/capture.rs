@@ -0,0 +1,192 @@
+//! Recording websocket sessions to JSONL and feeding them back in,
+//! so sessions players send us can be reproduced without their client.
+
+use crate::logging;
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+#[derive(Debug, Clone, Default)]
+pub enum Capture {
+    /// talk to the client, don't record anything
+    #[default]
+    None,
+    /// talk to the client and write every frame to the file
+    Record(PathBuf),
+    /// don't open sockets, read frames from the file instead.
+    /// `speed` scales the recorded delays, 0 replays without any delay
+    Replay { path: PathBuf, speed: f64 },
+}
+
+/// One raw websocket frame as it came from the client.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CaptureFrame {
+    /// milliseconds since the unix epoch
+    pub timestamp: i64,
+    pub socket: i32,
+    pub message: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct Recorder {
+    file: Arc<Mutex<BufWriter<File>>>,
+}
+
+impl Recorder {
+    pub fn create(path: &Path) -> std::io::Result<Self> {
+        let file = File::create(path)?;
+        Ok(Self {
+            file: Arc::new(Mutex::new(BufWriter::new(file))),
+        })
+    }
+
+    pub fn record(&self, socket: i32, message: &str) {
+        let frame = CaptureFrame {
+            timestamp: chrono::Utc::now().timestamp_millis(),
+            socket,
+            message: message.to_string(),
+        };
+        let mut file = self.file.lock().unwrap();
+        // flush every frame, captures are most useful from sessions that crashed
+        let written = serde_json::to_writer(&mut *file, &frame)
+            .map_err(std::io::Error::from)
+            .and_then(|_| file.write_all(b"\n"))
+            .and_then(|_| file.flush());
+        if let Err(e) = written {
+            log::warn!(target: logging::SOCKET, "failed to record frame: {:?}", e);
+        }
+    }
+}
+
+/// Read the frames recorded for `socket` and hand them to `on_frame`,
+/// sleeping between them like the original session did.
+/// Returns when the capture is exhausted or `on_frame` returns false.
+pub fn replay(
+    path: &Path,
+    socket: i32,
+    speed: f64,
+    mut on_frame: impl FnMut(&str) -> bool,
+) -> std::io::Result<()> {
+    let reader = BufReader::new(File::open(path)?);
+    let mut last: Option<i64> = None;
+    for line in reader.lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let frame = match serde_json::from_str::<CaptureFrame>(&line) {
+            Ok(frame) => frame,
+            Err(e) => {
+                log::warn!(target: logging::SOCKET, "skipping malformed frame: {:?}", e);
+                continue;
+            }
+        };
+        if frame.socket != socket {
+            continue;
+        }
+
+        if let Some(last) = last {
+            let delay = (frame.timestamp - last).max(0) as f64;
+            if speed > 0.0 {
+                std::thread::sleep(Duration::from_secs_f64(delay / 1000.0 / speed));
+            }
+        }
+        last = Some(frame.timestamp);
+
+        if !on_frame(&frame.message) {
+            break;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Instant;
+
+    /// A capture file of its own for every test.
+    fn path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("clowncher-{}-{}.jsonl", std::process::id(), name))
+    }
+
+    /// A frame as the recorder writes it.
+    fn line(timestamp: i64, message: &str) -> String {
+        let frame = CaptureFrame {
+            timestamp,
+            socket: 0,
+            message: message.to_string(),
+        };
+        serde_json::to_string(&frame).unwrap()
+    }
+
+    fn replayed(path: &Path, socket: i32) -> Vec<String> {
+        let mut frames = vec![];
+        replay(path, socket, 0.0, |frame| {
+            frames.push(frame.to_string());
+            true
+        })
+        .unwrap();
+        frames
+    }
+
+    #[test]
+    fn recorded_frames_replay_per_socket() {
+        let path = path("round-trip");
+        let recorder = Recorder::create(&path).unwrap();
+        recorder.record(0, r#"[8,"OnJsonApiEvent",{"uri":"/a"}]"#);
+        recorder.record(1, "other socket");
+        recorder.record(0, "line\nbreak and \"quotes\"");
+        drop(recorder);
+
+        assert_eq!(
+            replayed(&path, 0),
+            [
+                r#"[8,"OnJsonApiEvent",{"uri":"/a"}]"#,
+                "line\nbreak and \"quotes\""
+            ]
+        );
+        assert_eq!(replayed(&path, 1), ["other socket"]);
+        assert!(replayed(&path, 2).is_empty());
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn replay_skips_malformed_lines_and_stops_when_asked() {
+        let path = path("malformed");
+        let lines = [
+            line(0, "first"),
+            "not json".to_string(),
+            String::new(),
+            line(1, "second"),
+            line(2, "third"),
+        ];
+        std::fs::write(&path, lines.join("\n")).unwrap();
+
+        let mut frames = vec![];
+        replay(&path, 0, 0.0, |frame| {
+            frames.push(frame.to_string());
+            frames.len() < 2
+        })
+        .unwrap();
+        assert_eq!(frames, ["first", "second"]);
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn replay_keeps_the_recorded_delays() {
+        let path = path("delays");
+        let lines = [line(0, ""), line(200, "")];
+        std::fs::write(&path, lines.join("\n")).unwrap();
+
+        // twice as fast
+        let started = Instant::now();
+        replay(&path, 0, 2.0, |_| true).unwrap();
+        let elapsed = started.elapsed();
+        assert!(elapsed >= Duration::from_millis(100), "{:?}", elapsed);
+        std::fs::remove_file(path).unwrap();
+    }
+}
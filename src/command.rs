@@ -0,0 +1,363 @@
+//! Requests to the client, run on the tokio runtime instead of blocking the ui.
+//! Results come back as `Event::CommandDone` through the same channel as socket events.
+
+use crate::error::{Error, Result, WrapErr};
+use crate::event::Event;
+use crate::lcu;
+use crate::logging;
+use crate::queues::{self, GameQueue, QueueEligibility};
+use crate::ui::chat::{self, ChatMessage, Participant};
+use crate::ui::friendlist::groups::{self, FriendGroup, Membership};
+use crate::ui::game::invitations;
+use crate::ui::game::ranked::{self, RankedStats};
+use crossbeam::channel::Sender;
+use kassadin::client::LCU;
+use kassadin::routes;
+use kassadin::types::lcu::chat::Friend;
+use kassadin::types::lcu::lobby::PositionPreference;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::time::Duration;
+
+const TIMEOUT: Duration = Duration::from_secs(10);
+const RETRY_DELAY: Duration = Duration::from_millis(500);
+
+#[derive(Debug, Clone)]
+pub enum Command {
+    /// the queue as the client numbers it
+    JoinLobby(i64, PositionPreference),
+    LeaveLobby,
+    SetRoles(PositionPreference),
+    StartQueue,
+    StopQueue,
+    Accept,
+    Decline,
+    Invite(i64),
+    /// by invitation id
+    AcceptInvitation(String),
+    DeclineInvitation(String),
+    Kick(i64),
+    Dodge,
+    RankedStats(String),
+    LoadFriends,
+    /// the groups and who is in which
+    LoadFriendGroups,
+    /// by name
+    CreateFriendGroup(String),
+    /// group id and the new name
+    RenameFriendGroup(i64, String),
+    /// its friends move to the default group
+    DeleteFriendGroup(i64),
+    /// friend chat id and group id
+    MoveFriend(String, i64),
+    LoadQueues,
+    /// history and participants of a conversation, by id
+    LoadConversation(String),
+    /// like `LoadConversation`, but starts a direct conversation with the friend if there is none
+    OpenConversation(String),
+    /// conversation id, message type and body
+    SendMessage(String, &'static str, String),
+}
+
+impl Command {
+    /// What the user tried to do, for error messages.
+    pub fn label(&self) -> &'static str {
+        match self {
+            Command::JoinLobby(..) => "Join lobby",
+            Command::LeaveLobby => "Leave lobby",
+            Command::SetRoles(_) => "Set roles",
+            Command::StartQueue => "Start queue",
+            Command::StopQueue => "Stop queue",
+            Command::Accept => "Accept match",
+            Command::Decline => "Decline match",
+            Command::Invite(_) => "Invite friend",
+            Command::AcceptInvitation(_) => "Accept invitation",
+            Command::DeclineInvitation(_) => "Decline invitation",
+            Command::Kick(_) => "Kick member",
+            Command::Dodge => "Dodge",
+            Command::RankedStats(_) => "Load ranked stats",
+            Command::LoadFriends => "Load friends",
+            Command::LoadFriendGroups => "Load friend groups",
+            Command::CreateFriendGroup(_) => "Create friend group",
+            Command::RenameFriendGroup(..) => "Rename friend group",
+            Command::DeleteFriendGroup(_) => "Delete friend group",
+            Command::MoveFriend(..) => "Move friend",
+            Command::LoadQueues => "Load queues",
+            Command::LoadConversation(_) => "Load chat",
+            Command::OpenConversation(_) => "Open chat",
+            Command::SendMessage(..) => "Send message",
+        }
+    }
+
+    /// How often a failed or timed out request is repeated.
+    /// Only requests that don't hurt when they arrive twice are retried.
+    fn retries(&self) -> u32 {
+        match self {
+            Command::RankedStats(_)
+            | Command::LoadFriends
+            | Command::LoadFriendGroups
+            | Command::RenameFriendGroup(..)
+            | Command::MoveFriend(..)
+            | Command::LoadQueues
+            | Command::LoadConversation(_)
+            | Command::OpenConversation(_)
+            | Command::SetRoles(_)
+            | Command::LeaveLobby
+            | Command::StopQueue
+            | Command::Kick(_) => 2,
+            Command::Accept | Command::Decline | Command::DeclineInvitation(_) => 1,
+            Command::JoinLobby(..)
+            | Command::StartQueue
+            | Command::Invite(_)
+            | Command::AcceptInvitation(_)
+            | Command::SendMessage(..)
+            | Command::CreateFriendGroup(_)
+            | Command::DeleteFriendGroup(_)
+            | Command::Dodge => 0,
+        }
+    }
+}
+
+pub enum CommandOutput {
+    Done,
+    RankedStats(String, RankedStats),
+    Friends(Vec<Friend>),
+    FriendGroups(Vec<FriendGroup>, Vec<Membership>),
+    Queues(Vec<GameQueue>, Vec<QueueEligibility>),
+    /// conversation id, its history and who is in it
+    Conversation(String, Vec<ChatMessage>, Vec<Participant>),
+}
+
+pub struct CommandQueue {
+    /// the instance whose client runs these commands
+    instance: i32,
+    lcu: LCU,
+    results: Sender<Event>,
+    next_id: u64,
+    pending: HashMap<u64, Command>,
+}
+
+impl CommandQueue {
+    pub fn new(instance: i32, lcu: LCU, results: Sender<Event>) -> Self {
+        Self {
+            instance,
+            lcu,
+            results,
+            next_id: 0,
+            pending: HashMap::new(),
+        }
+    }
+
+    /// Point new commands at a (re)started client.
+    pub fn set_lcu(&mut self, lcu: LCU) {
+        self.lcu = lcu;
+    }
+
+    pub fn send(&mut self, command: Command) -> u64 {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.pending.insert(id, command.clone());
+        match command {
+            // sent on their own, not clicked
+            Command::RankedStats(_)
+            | Command::LoadFriends
+            | Command::LoadFriendGroups
+            | Command::LoadQueues
+            | Command::LoadConversation(_) => {
+                log::debug!(target: logging::INTEROP, "{}: {:?}", self.instance, command)
+            }
+            _ => log::info!(target: logging::UI, "{}: {:?}", self.instance, command),
+        }
+
+        let instance = self.instance;
+        let lcu = self.lcu.clone();
+        let results = self.results.clone();
+        crate::RT.spawn(async move {
+            let result = execute_with_retries(&lcu, &command)
+                .await
+                .wrap_err(command.label());
+            let _ = results.send(Event::CommandDone(instance, id, command, result));
+        });
+        id
+    }
+
+    pub fn finish(&mut self, id: u64) {
+        self.pending.remove(&id);
+    }
+
+    /// Whether a command matching `f` is still in flight, used to disable its button.
+    pub fn is_pending(&self, f: impl Fn(&Command) -> bool) -> bool {
+        self.pending.values().any(f)
+    }
+}
+
+/// Run a command on the calling thread, for callers without a ui loop like `clowncher-cli`.
+pub fn run_blocking(lcu: &LCU, command: &Command) -> Result<CommandOutput> {
+    crate::RT
+        .block_on(execute_with_retries(lcu, command))
+        .wrap_err(command.label())
+}
+
+/// `GET uri` on the calling thread, for state no command covers, e.g. to hydrate after connecting.
+pub fn get_blocking(lcu: &LCU, uri: &str) -> Result<Value> {
+    crate::RT.block_on(async {
+        match tokio::time::timeout(TIMEOUT, lcu::get::<Value>(lcu, uri)).await {
+            Ok(result) => result,
+            Err(_) => Err(Error::Timeout(TIMEOUT)).wrap_err_with(|| format!("GET {}", uri)),
+        }
+    })
+}
+
+async fn execute_with_retries(lcu: &LCU, command: &Command) -> Result<CommandOutput> {
+    let mut attempt = 0;
+    loop {
+        let error = match tokio::time::timeout(TIMEOUT, execute(lcu, command)).await {
+            Ok(Ok(output)) => return Ok(output),
+            Ok(Err(e)) => e,
+            Err(_) => Error::Timeout(TIMEOUT).into(),
+        };
+        if attempt >= command.retries() {
+            return Err(error);
+        }
+        attempt += 1;
+        tokio::time::sleep(RETRY_DELAY).await;
+    }
+}
+
+/// kassadin's own methods take error statuses for answers, these go through `crate::lcu`.
+async fn execute(lcu: &LCU, command: &Command) -> Result<CommandOutput> {
+    match command {
+        Command::JoinLobby(queue_id, positions) => {
+            // `join_lobby` only takes the queues `QueueId` knows
+            lcu::post::<Value>(lcu, routes::lobby::LOBBY, &json!({ "queueId": queue_id })).await?;
+            done(lcu::put::<Value>(lcu, routes::lobby::POSITION_PREF, positions).await)
+        }
+        Command::LeaveLobby => done(lcu::delete::<Value>(lcu, routes::lobby::LOBBY).await),
+        Command::SetRoles(positions) => {
+            done(lcu::put::<Value>(lcu, routes::lobby::POSITION_PREF, positions).await)
+        }
+        Command::StartQueue => done(lcu::post_empty(lcu, routes::lobby::SEARCH).await),
+        Command::StopQueue => done(lcu::delete::<Value>(lcu, routes::lobby::SEARCH).await),
+        Command::Accept => done(lcu::post_empty(lcu, routes::matchmaking::ACCEPT).await),
+        Command::Decline => done(lcu::post_empty(lcu, routes::matchmaking::DECLINE).await),
+        Command::Invite(summoner_id) => done(
+            lcu::post::<Value>(
+                lcu,
+                routes::lobby::INVITATIONS,
+                &json!([{ "toSummonerId": summoner_id }]),
+            )
+            .await,
+        ),
+        Command::AcceptInvitation(id) => done(
+            lcu::post_empty(
+                lcu,
+                &format!("{}/{}/accept", invitations::RECEIVED_INVITATIONS, id),
+            )
+            .await,
+        ),
+        Command::DeclineInvitation(id) => done(
+            lcu::post_empty(
+                lcu,
+                &format!("{}/{}/decline", invitations::RECEIVED_INVITATIONS, id),
+            )
+            .await,
+        ),
+        Command::Kick(summoner_id) => {
+            let uri = routes::lobby::MEMBERS_KICK.replace("{id}", &summoner_id.to_string());
+            done(lcu::post_empty(lcu, &uri).await)
+        }
+        Command::Dodge => done(lcu::post_empty(lcu, routes::login::DODGE_LOBBY).await),
+        Command::RankedStats(puuid) => {
+            lcu::get::<RankedStats>(lcu, &format!("{}/{}", ranked::RANKED_STATS, puuid))
+                .await
+                .map(|stats| CommandOutput::RankedStats(puuid.clone(), stats))
+        }
+        Command::LoadFriends => lcu::get::<Vec<Friend>>(lcu, routes::chat::FRIENDS)
+            .await
+            .map(CommandOutput::Friends),
+        Command::LoadFriendGroups => load_friend_groups(lcu).await,
+        Command::CreateFriendGroup(name) => {
+            lcu::post::<Value>(lcu, groups::FRIEND_GROUPS, &json!({ "name": name })).await?;
+            load_friend_groups(lcu).await
+        }
+        Command::RenameFriendGroup(id, name) => {
+            lcu::put::<Value>(
+                lcu,
+                &format!("{}/{}", groups::FRIEND_GROUPS, id),
+                &json!({ "name": name }),
+            )
+            .await?;
+            load_friend_groups(lcu).await
+        }
+        Command::DeleteFriendGroup(id) => {
+            lcu::delete::<Value>(lcu, &format!("{}/{}", groups::FRIEND_GROUPS, id)).await?;
+            load_friend_groups(lcu).await
+        }
+        Command::MoveFriend(id, group_id) => {
+            lcu::put::<Value>(
+                lcu,
+                &format!("{}/{}", routes::chat::FRIENDS, chat::escape(id)),
+                &json!({ "groupId": group_id }),
+            )
+            .await?;
+            load_friend_groups(lcu).await
+        }
+        Command::LoadQueues => {
+            let queues = lcu::get::<Vec<GameQueue>>(lcu, queues::QUEUES).await?;
+            // without eligibility every available queue is offered, the client still checks
+            let eligibility = match lcu::post::<Vec<QueueEligibility>>(
+                lcu,
+                queues::ELIGIBILITY,
+                &json!({}),
+            )
+            .await
+            {
+                Ok(eligibility) => eligibility,
+                Err(e) => {
+                    log::warn!(target: logging::LOBBY, "queue eligibility failed: {}", e);
+                    vec![]
+                }
+            };
+            Ok(CommandOutput::Queues(queues, eligibility))
+        }
+        Command::LoadConversation(id) => load_conversation(lcu, id).await,
+        Command::OpenConversation(id) => {
+            lcu::post::<Value>(
+                lcu,
+                chat::CONVERSATIONS,
+                &json!({ "id": id, "type": chat::DIRECT }),
+            )
+            .await?;
+            load_conversation(lcu, id).await
+        }
+        Command::SendMessage(id, kind, body) => done(
+            lcu::post::<Value>(
+                lcu,
+                &format!("{}/messages", chat::conversation_uri(id)),
+                &json!({ "body": body, "type": kind }),
+            )
+            .await,
+        ),
+    }
+}
+
+async fn load_friend_groups(lcu: &LCU) -> Result<CommandOutput> {
+    let groups = lcu::get::<Vec<FriendGroup>>(lcu, groups::FRIEND_GROUPS).await?;
+    let members = lcu::get::<Vec<Membership>>(lcu, routes::chat::FRIENDS).await?;
+    Ok(CommandOutput::FriendGroups(groups, members))
+}
+
+async fn load_conversation(lcu: &LCU, id: &str) -> Result<CommandOutput> {
+    let uri = chat::conversation_uri(id);
+    let messages = lcu::get::<Vec<ChatMessage>>(lcu, &format!("{}/messages", uri)).await?;
+    let participants = lcu::get::<Vec<Participant>>(lcu, &format!("{}/participants", uri)).await?;
+    Ok(CommandOutput::Conversation(
+        id.to_string(),
+        messages,
+        participants,
+    ))
+}
+
+fn done<T>(result: Result<T>) -> Result<CommandOutput> {
+    result.map(|_| CommandOutput::Done)
+}
@@ -0,0 +1,316 @@
+//! Keeps a websocket to the client alive: waits for the client, reconnects with backoff
+//! after drops and picks up new credentials when the client restarted.
+//! Every connect starts with the client's current state, live events follow.
+
+use crate::capture::Recorder;
+use crate::command;
+use crate::error::Error;
+use crate::event::Event;
+use crate::lcu;
+use crate::logging;
+use crate::router::Router;
+use crate::ui::game::{champ_select, invitations};
+use crate::ui::{account, chat};
+use crossbeam::channel::{Receiver, RecvTimeoutError, Sender};
+use kassadin::client::{WebSocket, LCU};
+use kassadin::routes;
+use kassadin::types::socket::EventType;
+use std::collections::HashMap;
+use std::net::{Shutdown, TcpStream};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use websocket::{OwnedMessage, WebSocketResult};
+
+/// a frame as it was read and when
+type Frame = (Instant, WebSocketResult<OwnedMessage>);
+
+const BACKOFF_START: Duration = Duration::from_millis(500);
+const BACKOFF_MAX: Duration = Duration::from_secs(30);
+/// how often to look for the client while it isn't running
+const CLIENT_POLL: Duration = Duration::from_secs(2);
+/// how often a quiet socket checks whether it should stop
+const STOP_POLL: Duration = Duration::from_millis(250);
+/// failed connects in a row before giving up on a client, about two minutes with backoff
+const MAX_ATTEMPTS: u32 = 10;
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub enum ConnectionStatus {
+    WaitingForClient,
+    #[default]
+    Connecting,
+    Connected,
+    /// `error` is why the last attempt failed, `None` right after the socket dropped
+    Reconnecting {
+        attempt: u32,
+        error: Option<String>,
+    },
+    /// gave up after `MAX_ATTEMPTS`, tries again once the client restarts
+    Failed(String),
+    Closed,
+}
+
+impl ConnectionStatus {
+    pub fn label(&self) -> String {
+        match self {
+            Self::WaitingForClient => "Waiting for client".to_string(),
+            Self::Connecting => "Connecting".to_string(),
+            Self::Connected => "Connected".to_string(),
+            Self::Reconnecting {
+                attempt,
+                error: None,
+            } => format!("Reconnecting ({})", attempt),
+            Self::Reconnecting {
+                attempt,
+                error: Some(error),
+            } => format!("Reconnecting ({}), {}", attempt, error),
+            Self::Failed(error) => format!("Couldn't connect, {}", error),
+            Self::Closed => "Disconnected".to_string(),
+        }
+    }
+
+    pub fn to_color(&self) -> egui::Color32 {
+        match self {
+            Self::Connected => crate::ui::colors::GREEN_A400,
+            Self::Connecting | Self::Reconnecting { .. } => crate::ui::colors::YELLOW_A800,
+            Self::WaitingForClient => crate::ui::colors::GRAY_A500,
+            Self::Failed(_) | Self::Closed => crate::ui::colors::RED_A500,
+        }
+    }
+}
+
+/// Where the client's port and password come from.
+#[derive(Clone)]
+pub enum Credentials {
+    /// whatever client is running right now, re-read on every reconnect
+    Lockfile,
+    /// a client that never moves, e.g. `crate::mock`
+    Fixed(LCU),
+}
+
+impl Credentials {
+    /// Fresh credentials, `None` while no client is running.
+    pub fn read(&self) -> Option<LCU> {
+        match self {
+            Self::Lockfile => lcu::find(),
+            Self::Fixed(lcu) => Some(lcu.clone()),
+        }
+    }
+}
+
+/// Fetched after every connect and handed on before any live event. Frames that arrived
+/// before a resource's answer are dropped, the answer already has what they said.
+/// Gameflow goes first, the rest refines the state it sets.
+const HYDRATE: [&str; 7] = [
+    routes::game_flow::SESSION,
+    routes::lobby::LOBBY,
+    invitations::RECEIVED_INVITATIONS,
+    routes::matchmaking::SEARCH,
+    champ_select::SESSION,
+    account::ME,
+    chat::CONVERSATIONS,
+];
+
+fn backoff(attempt: u32) -> Duration {
+    BACKOFF_START
+        .checked_mul(1 << attempt.min(6))
+        .unwrap_or(BACKOFF_MAX)
+        .min(BACKOFF_MAX)
+}
+
+/// Runs until `stop` is set, pushing status, new clients
+/// and everything the socket receives into `app` as it arrives.
+pub fn run(
+    idx: i32,
+    credentials: Credentials,
+    router: Arc<Router>,
+    recorder: Option<Recorder>,
+    app: Sender<Event>,
+    stop: Arc<AtomicBool>,
+) {
+    let mut current = None;
+    let mut status = |status: ConnectionStatus| {
+        if current.as_ref() == Some(&status) {
+            return;
+        }
+        log::debug!(target: logging::SOCKET, "socket {}: {:?}", idx, status);
+        current = Some(status.clone());
+        let _ = app.send(Event::Connection(idx, status));
+    };
+    let mut attempt = 0;
+    let mut error = None;
+    // port and password of the client we gave up on, only a restarted client is worth a retry
+    let mut gave_up = None;
+
+    while !stop.load(Ordering::Relaxed) {
+        let lcu = match credentials.read() {
+            Some(lcu) => lcu,
+            None => {
+                status(ConnectionStatus::WaitingForClient);
+                // not an error, it just isn't started yet or exited
+                attempt = 0;
+                gave_up = None;
+                std::thread::sleep(CLIENT_POLL);
+                continue;
+            }
+        };
+        let client = lcu
+            .info
+            .as_ref()
+            .map(|info| (info.port.clone(), info.token.clone()));
+        if gave_up.is_some() {
+            if gave_up == client {
+                std::thread::sleep(CLIENT_POLL);
+                continue;
+            }
+            gave_up = None;
+            attempt = 0;
+        }
+
+        status(if attempt == 0 {
+            ConnectionStatus::Connecting
+        } else {
+            ConnectionStatus::Reconnecting {
+                attempt,
+                error: error.clone(),
+            }
+        });
+
+        // failures are routine while the client starts or restarts, the status shows them
+        // and only giving up is worth a toast
+        let socket = match lcu::connect(&lcu) {
+            Ok(socket) => socket,
+            Err(e) => {
+                log::warn!(target: logging::SOCKET, "socket {}: connect failed: {}", idx, e);
+                attempt += 1;
+                if attempt >= MAX_ATTEMPTS {
+                    status(ConnectionStatus::Failed(e.to_string()));
+                    let e = e.wrap_err(format!("Connect to client ({})", idx));
+                    let _ = app.send(Event::Error(e));
+                    gave_up = client;
+                } else {
+                    error = Some(e.to_string());
+                    std::thread::sleep(backoff(attempt - 1));
+                }
+                continue;
+            }
+        };
+
+        attempt = 0;
+        error = None;
+        status(ConnectionStatus::Connected);
+        // the client may have restarted with a new port and password, state has to be re-synced
+        if app.send(Event::Reconnected(idx, lcu.clone())).is_err() {
+            return;
+        }
+        // frames are read from here on, they queue up while the snapshot is fetched
+        let (frames, shutdown) = match read_frames(socket) {
+            Ok(reader) => reader,
+            Err(e) => {
+                log::warn!(target: logging::SOCKET, "socket {}: {:?}", idx, e);
+                error = Some(e.to_string());
+                std::thread::sleep(backoff(0));
+                attempt = 1;
+                continue;
+            }
+        };
+        let fetched = match hydrate(idx, &lcu, &router, &app) {
+            Some(fetched) => fetched,
+            None => return,
+        };
+
+        while !stop.load(Ordering::Relaxed) {
+            let (received, message) = match frames.recv_timeout(STOP_POLL) {
+                Ok(frame) => frame,
+                // a quiet client, only `stop` needs another look
+                Err(RecvTimeoutError::Timeout) => continue,
+                Err(RecvTimeoutError::Disconnected) => break,
+            };
+            match message {
+                Ok(OwnedMessage::Text(message)) => {
+                    if let Some(recorder) = &recorder {
+                        recorder.record(idx, &message);
+                    }
+                    let event = match router.dispatch(&message) {
+                        Some(event) => event,
+                        None => continue,
+                    };
+                    // the client answered with newer state than this frame, don't roll it back
+                    let stale = fetched
+                        .get(event.uri.as_str())
+                        .is_some_and(|fetched| received < *fetched);
+                    if stale {
+                        log::trace!(target: logging::SOCKET, "socket {}: stale {}", idx, event.uri);
+                        continue;
+                    }
+                    if app.send(Event::LeagueEvent(idx, event)).is_err() {
+                        return;
+                    }
+                }
+                Ok(OwnedMessage::Close(_)) => break,
+                Ok(_) => {}
+                Err(e) => {
+                    let e = Error::socket(e);
+                    log::warn!(target: logging::SOCKET, "socket {}: read failed: {}", idx, e);
+                    error = Some(e.to_string());
+                    break;
+                }
+            }
+        }
+        // the reader may be blocked on a client that went quiet
+        let _ = shutdown.shutdown(Shutdown::Both);
+
+        if !stop.load(Ordering::Relaxed) {
+            status(ConnectionStatus::Reconnecting {
+                attempt: 1,
+                error: error.clone(),
+            });
+            std::thread::sleep(backoff(attempt));
+            attempt = 1;
+        }
+    }
+
+    status(ConnectionStatus::Closed);
+}
+
+/// Reads `socket` on its own thread, each frame with when it arrived.
+/// The stream is for shutting the socket down, which also ends the thread.
+fn read_frames(socket: WebSocket) -> std::io::Result<(Receiver<Frame>, TcpStream)> {
+    let shutdown = socket.client.stream_ref().get_ref().try_clone()?;
+    let (send, frames) = crossbeam::channel::unbounded();
+    let mut client = socket.client;
+    std::thread::spawn(move || {
+        for message in client.incoming_messages() {
+            let failed = message.is_err();
+            if send.send((Instant::now(), message)).is_err() || failed {
+                return;
+            }
+        }
+    });
+    Ok((frames, shutdown))
+}
+
+/// Sends the current state of everything in `HYDRATE` to `app` as if the client pushed it.
+/// Returns when each answer arrived, `None` once nobody listens anymore.
+fn hydrate(
+    idx: i32,
+    lcu: &LCU,
+    router: &Router,
+    app: &Sender<Event>,
+) -> Option<HashMap<&'static str, Instant>> {
+    let mut fetched = HashMap::new();
+    for uri in HYDRATE {
+        match command::get_blocking(lcu, uri) {
+            Ok(data) => {
+                fetched.insert(uri, Instant::now());
+                let event = router.decode(uri, Some(EventType::Update), &data);
+                if app.send(Event::LeagueEvent(idx, event)).is_err() {
+                    return None;
+                }
+            }
+            // e.g. not in a lobby right now, the reset before already covers that
+            Err(e) => log::debug!(target: logging::SOCKET, "socket {}: no {}: {:?}", idx, uri, e),
+        }
+    }
+    Some(fetched)
+}
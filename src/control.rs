@@ -0,0 +1,459 @@
+//! Localhost API for Stream Deck buttons, dashboards and the like.
+//!
+//! * `GET /state` returns every instance's state as JSON
+//! * a websocket on any path gets that state on connect and again whenever it changes
+//! * `POST /queue/start`, `/queue/stop`, `/accept`, `/decline`, `/lobby/leave` and `/dodge`
+//! * `POST /roles` with `{"first": "MIDDLE", "second": "TOP"}`
+//! * `POST /invite` with `{"name": "friend"}` or `{"summonerId": 123}`
+//!
+//! Everything needs `Authorization: Bearer <token>`, websockets may pass `?token=<token>` instead.
+//! Actions go to the selected instance unless `?instance=<id>` says otherwise
+//! and are handed to `match_events` as `Event::Control`, like any other event.
+
+use crate::command::Command;
+use crate::event::Event;
+use crate::http;
+use crate::instance::Instances;
+use crate::logging;
+use crossbeam::channel::Sender;
+use kassadin::types::lcu::consts::Position;
+use kassadin::types::lcu::lobby::PositionPreference;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::io::{Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use websocket::server::upgrade::Request as Head;
+use websocket::sync::server::upgrade::{Buffer, IntoWs, Upgrade};
+use websocket::OwnedMessage;
+
+/// how long a client may take to send its request
+const READ_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ControlConfig {
+    pub enabled: bool,
+    pub port: u16,
+    /// generated on first start
+    pub token: String,
+}
+
+impl Default for ControlConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            port: 8765,
+            token: String::new(),
+        }
+    }
+}
+
+impl ControlConfig {
+    pub fn load() -> Self {
+        let mut config = confy::load::<ControlConfig>("clowncher/control").unwrap_or_default();
+        if config.token.is_empty() {
+            match random_token() {
+                Ok(token) => config.token = token,
+                Err(e) => {
+                    // without a token anyone could drive the client
+                    log::error!(target: logging::INTEROP, "can't generate control token: {}", e);
+                    config.enabled = false;
+                    return config;
+                }
+            }
+            if let Err(e) = confy::store("clowncher/control", config.clone()) {
+                log::warn!(target: logging::INTEROP, "can't store control token: {}", e);
+            }
+        }
+        config
+    }
+}
+
+/// What a control request asks of an instance.
+#[derive(Debug, Clone)]
+pub enum ControlAction {
+    Command(Command),
+    /// also becomes the positions the ui shows
+    SetRoles(PositionPreference),
+    /// by summoner id, like `InviteFriend` skipped if they are already in or invited
+    Invite(i64),
+    /// by name, looked up in the instance's friendlist
+    InviteFriend(String),
+}
+
+/// Handle to the running control server.
+#[derive(Clone)]
+pub struct ControlServer {
+    pub port: u16,
+    pub token: String,
+    /// the last published state, serialized
+    state: Arc<Mutex<String>>,
+    subscribers: Arc<Mutex<Vec<Sender<String>>>>,
+    app: Sender<Event>,
+}
+
+impl ControlServer {
+    /// Push `state` to websocket clients, if it changed.
+    pub fn publish(&self, state: Value) {
+        let state = state.to_string();
+        let mut last = self.state.lock().unwrap();
+        if *last == state {
+            return;
+        }
+        *last = state.clone();
+        let mut subscribers = self.subscribers.lock().unwrap();
+        subscribers.retain(|subscriber| subscriber.send(state.clone()).is_ok());
+    }
+
+    fn authorized(&self, authorization: Option<&str>, query: Option<&str>) -> bool {
+        authorization == Some(format!("Bearer {}", self.token).as_str())
+            || query_param(query, "token") == Some(self.token.as_str())
+    }
+
+    /// A connection on its own thread, so a slow client only holds up itself.
+    fn accept(&self, stream: TcpStream) {
+        if stream.set_read_timeout(Some(READ_TIMEOUT)).is_err() {
+            return;
+        }
+        match stream.into_ws() {
+            Ok(upgrade) => self.subscribe(upgrade),
+            Err((mut stream, Some(head), buffer, _)) => {
+                let _ = self.serve(&mut stream, head, buffer);
+            }
+            Err(_) => {}
+        }
+    }
+
+    /// Send the state to a websocket client, now and on every change.
+    fn subscribe(&self, upgrade: Upgrade<TcpStream>) {
+        let authorization = upgrade
+            .request
+            .headers
+            .get_raw("Authorization")
+            .and_then(|raw| raw.first())
+            .map(|raw| String::from_utf8_lossy(raw).to_string());
+        let uri = upgrade.uri();
+        let query = uri.split_once('?').map(|(_, query)| query);
+        if !self.authorized(authorization.as_deref(), query) {
+            let _ = upgrade.reject();
+            return;
+        }
+
+        let (send, recv) = crossbeam::channel::unbounded::<String>();
+        let current = {
+            // under the state lock, so no update gets lost in between
+            let state = self.state.lock().unwrap();
+            self.subscribers.lock().unwrap().push(send);
+            state.clone()
+        };
+        let mut socket = match upgrade.accept() {
+            Ok(socket) => socket,
+            Err(_) => return,
+        };
+        for state in std::iter::once(current).chain(recv) {
+            if socket.send_message(&OwnedMessage::Text(state)).is_err() {
+                break;
+            }
+        }
+    }
+
+    /// Answer a plain request, the body is only read once the token checks out.
+    fn serve<S: Read + Write>(
+        &self,
+        stream: &mut S,
+        head: Head,
+        buffer: Option<Buffer>,
+    ) -> std::io::Result<()> {
+        let mut request = http::Request::parse(head);
+        let (status, body) =
+            if !self.authorized(request.authorization.as_deref(), request.query.as_deref()) {
+                (401, json!({ "error": "missing or wrong token" }))
+            } else if request.content_length > http::MAX_BODY {
+                (413, json!({ "error": "body too large" }))
+            } else {
+                request.read_body(stream, buffer)?;
+                self.handle(&request)
+            };
+        http::respond(stream, status, &body)
+    }
+
+    fn handle(&self, request: &http::Request) -> (u16, Value) {
+        let instance =
+            query_param(request.query.as_deref(), "instance").and_then(|id| id.parse().ok());
+
+        let action = match (request.method.as_str(), request.path.as_str()) {
+            ("GET", "/state") => {
+                let state = self.state.lock().unwrap();
+                return (200, serde_json::from_str(&state).unwrap_or(Value::Null));
+            }
+            ("POST", "/queue/start") => ControlAction::Command(Command::StartQueue),
+            ("POST", "/queue/stop") => ControlAction::Command(Command::StopQueue),
+            ("POST", "/accept") => ControlAction::Command(Command::Accept),
+            ("POST", "/decline") => ControlAction::Command(Command::Decline),
+            ("POST", "/lobby/leave") => ControlAction::Command(Command::LeaveLobby),
+            ("POST", "/dodge") => ControlAction::Command(Command::Dodge),
+            ("POST", "/roles") => {
+                let body = request.json();
+                let position = |key: &str| match &body[key] {
+                    Value::Null => Ok(None),
+                    value => serde_json::from_value::<Position>(value.clone()).map(Some),
+                };
+                match (position("first"), position("second")) {
+                    (Ok(first_preference), Ok(second_preference)) => {
+                        ControlAction::SetRoles(PositionPreference {
+                            first_preference,
+                            second_preference,
+                        })
+                    }
+                    _ => {
+                        let error = "positions are TOP, JUNGLE, MIDDLE, BOTTOM, UTILITY or FILL";
+                        return (400, json!({ "error": error }));
+                    }
+                }
+            }
+            ("POST", "/invite") => {
+                let body = request.json();
+                if let Some(summoner_id) = body["summonerId"].as_i64() {
+                    ControlAction::Invite(summoner_id)
+                } else if let Some(name) = body["name"].as_str() {
+                    ControlAction::InviteFriend(name.to_string())
+                } else {
+                    return (400, json!({ "error": "expected name or summonerId" }));
+                }
+            }
+            _ => return (404, json!({ "error": "not found" })),
+        };
+
+        log::info!(target: logging::INTEROP, "control: {:?}", action);
+        if self.app.send(Event::Control(instance, action)).is_err() {
+            return (500, json!({ "error": "shutting down" }));
+        }
+        // the outcome shows up in `/state`, failures in the app
+        (202, json!({ "accepted": true }))
+    }
+}
+
+/// Listen on localhost, actions are sent to `app`.
+pub fn spawn(config: &ControlConfig, app: Sender<Event>) -> std::io::Result<ControlServer> {
+    let listener = TcpListener::bind(("127.0.0.1", config.port))?;
+    let control = ControlServer {
+        port: listener.local_addr()?.port(),
+        token: config.token.clone(),
+        state: Arc::new(Mutex::new(Value::Null.to_string())),
+        subscribers: Arc::new(Mutex::new(vec![])),
+        app,
+    };
+    log::info!(target: logging::INTEROP, "control api on port {}", control.port);
+
+    let server_control = control.clone();
+    std::thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            let control = server_control.clone();
+            std::thread::spawn(move || control.accept(stream));
+        }
+    });
+
+    Ok(control)
+}
+
+/// Everything the api publishes, built after every batch of events.
+pub fn snapshot(instances: &Instances) -> Value {
+    let list = instances
+        .list
+        .iter()
+        .map(|instance| {
+            let game = &instance.game;
+            let members = game
+                .members
+                .iter()
+                .map(|member| {
+                    let positions = &member.positions;
+                    let ranked = member
+                        .puuid
+                        .as_deref()
+                        .and_then(|puuid| game.ranked.get(puuid))
+                        .map(|ranked| {
+                            ranked
+                                .queue_map
+                                .values()
+                                .map(|queue| {
+                                    json!({
+                                        "queueType": queue.queue_type,
+                                        "tier": queue.tier,
+                                        "division": queue.division,
+                                        "lp": queue.league_points,
+                                        "wins": queue.wins,
+                                        "losses": queue.losses,
+                                        "provisional": queue.is_provisional,
+                                    })
+                                })
+                                .collect::<Vec<_>>()
+                        });
+                    json!({
+                        "name": member.name,
+                        "summonerId": member.summoner_id,
+                        "puuid": member.puuid,
+                        "leader": member.leader,
+                        "autofillable": member.autofillable,
+                        "firstPosition": positions.first_preference.map(|p| p.to_string()),
+                        "secondPosition": positions.second_preference.map(|p| p.to_string()),
+                        "ranked": ranked,
+                    })
+                })
+                .collect::<Vec<_>>();
+            let friends = instance
+                .friendlist
+                .friends
+                .iter()
+                .map(|friend| {
+                    json!({
+                        "name": friend.name,
+                        "summonerId": friend.summoner_id,
+                        "status": format!("{:?}", friend.status),
+                    })
+                })
+                .collect::<Vec<_>>();
+
+            json!({
+                "id": instance.id,
+                "name": instance.name,
+                "connection": instance.connection.label(),
+                "searchState": format!("{:?}", game.flow.state()),
+                "stateSince": game.flow.entered().map(|entered| entered.to_rfc3339()),
+                "queueId": game.queue_id,
+                "queueTimer": game.queue_timer,
+                "estimatedQueueTime": game.estimated_queue_time,
+                "members": members,
+                "friends": friends,
+            })
+        })
+        .collect::<Vec<_>>();
+
+    json!({
+        "selected": instances.selected,
+        "instances": list,
+    })
+}
+
+fn query_param<'a>(query: Option<&'a str>, name: &str) -> Option<&'a str> {
+    query?
+        .split('&')
+        .filter_map(|pair| pair.split_once('='))
+        .find(|(key, _)| *key == name)
+        .map(|(_, value)| value)
+}
+
+/// 128 bits from the OS's random source, as hex.
+fn random_token() -> Result<String, getrandom::Error> {
+    let mut bytes = [0u8; 16];
+    getrandom::getrandom(&mut bytes)?;
+    Ok(bytes.iter().map(|byte| format!("{:02x}", byte)).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crossbeam::channel::Receiver;
+
+    fn server(token: &str) -> (ControlServer, Receiver<Event>) {
+        let (app, events) = crossbeam::channel::unbounded();
+        let config = ControlConfig {
+            enabled: true,
+            port: 0,
+            token: token.to_string(),
+        };
+        (spawn(&config, app).unwrap(), events)
+    }
+
+    /// Send `raw` as is and return the whole response.
+    fn send(control: &ControlServer, raw: &str) -> String {
+        let mut stream = TcpStream::connect(("127.0.0.1", control.port)).unwrap();
+        stream.set_read_timeout(Some(READ_TIMEOUT)).unwrap();
+        stream.write_all(raw.as_bytes()).unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        response
+    }
+
+    #[test]
+    fn bearer_or_query_token_authorizes() {
+        let (control, _events) = server("secret");
+        assert!(control.authorized(Some("Bearer secret"), None));
+        assert!(control.authorized(None, Some("token=secret")));
+        assert!(control.authorized(None, Some("instance=2&token=secret")));
+    }
+
+    #[test]
+    fn missing_or_wrong_tokens_are_rejected() {
+        let (control, _events) = server("secret");
+        assert!(!control.authorized(None, None));
+        assert!(!control.authorized(Some("Bearer wrong"), None));
+        assert!(!control.authorized(Some("Bearer "), None));
+        assert!(!control.authorized(Some("secret"), None));
+        assert!(!control.authorized(Some("Bearer secret2"), None));
+        assert!(!control.authorized(None, Some("token=wrong")));
+        assert!(!control.authorized(None, Some("token=")));
+        assert!(!control.authorized(None, Some("secret")));
+        assert!(!control.authorized(None, Some("instance=secret")));
+    }
+
+    #[test]
+    fn tokens_are_random() {
+        let token = random_token().unwrap();
+        assert_eq!(token.len(), 32);
+        assert_ne!(token, random_token().unwrap());
+    }
+
+    #[test]
+    fn actions_reach_the_app() {
+        let (control, events) = server("secret");
+        let response = send(
+            &control,
+            "POST /invite?instance=3 HTTP/1.1\r\nAuthorization: Bearer secret\r\n\
+             Content-Length: 17\r\n\r\n{\"summonerId\":42}",
+        );
+        assert!(response.starts_with("HTTP/1.1 202"), "{}", response);
+        match events.recv_timeout(READ_TIMEOUT).unwrap() {
+            Event::Control(Some(3), ControlAction::Invite(42)) => {}
+            _ => panic!("not the invite"),
+        }
+    }
+
+    #[test]
+    fn unauthorized_bodies_are_never_read() {
+        let (control, events) = server("secret");
+        let response = send(
+            &control,
+            "POST /accept HTTP/1.1\r\nContent-Length: 99999999999\r\n\r\n",
+        );
+        assert!(response.starts_with("HTTP/1.1 401"), "{}", response);
+        assert!(events.try_recv().is_err());
+    }
+
+    #[test]
+    fn large_bodies_are_refused() {
+        let (control, events) = server("secret");
+        let response = send(
+            &control,
+            "POST /roles HTTP/1.1\r\nAuthorization: Bearer secret\r\n\
+             Content-Length: 99999999999\r\n\r\n",
+        );
+        assert!(response.starts_with("HTTP/1.1 413"), "{}", response);
+        assert!(events.try_recv().is_err());
+    }
+
+    #[test]
+    fn stalled_clients_dont_block_others() {
+        let (control, _events) = server("secret");
+        let mut stalled = TcpStream::connect(("127.0.0.1", control.port)).unwrap();
+        stalled.write_all(b"GET /state HTTP/1.1\r\n").unwrap();
+
+        let response = send(
+            &control,
+            "GET /state HTTP/1.1\r\nAuthorization: Bearer secret\r\n\r\n",
+        );
+        assert!(response.starts_with("HTTP/1.1 200"), "{}", response);
+    }
+}
@@ -0,0 +1,132 @@
+//! Finds running clients through their `LeagueClientUx` process, which carries port and password
+//! on its command line. Clients in different Wine prefixes show up as separate processes.
+//! Linux reads `/proc` for the Wine prefixes, elsewhere sysinfo lists the processes.
+
+use crate::event::Event;
+use crate::lcu;
+#[cfg(not(target_os = "linux"))]
+use crate::logging;
+use crossbeam::channel::Sender;
+use kassadin::client::LCU;
+use std::time::Duration;
+
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ClientProcess {
+    pub pid: u32,
+    pub port: u16,
+    pub password: String,
+    /// what the instance is called in the ui
+    pub label: String,
+    /// the Wine prefix it runs in, if any
+    pub prefix: Option<String>,
+}
+
+impl ClientProcess {
+    pub fn lcu(&self) -> LCU {
+        lcu::from_credentials(self.port, &self.password)
+    }
+
+    fn from_args(pid: u32, args: &[String], prefix: Option<String>) -> Option<Self> {
+        let arg = |name: &str| args.iter().find_map(|arg| arg.strip_prefix(name));
+        let client = Self {
+            pid,
+            port: arg("--app-port=")?.parse().ok()?,
+            password: arg("--remoting-auth-token=")?.to_string(),
+            label: prefix.clone().unwrap_or_else(|| format!("Client {}", pid)),
+            prefix,
+        };
+        Some(client)
+    }
+}
+
+/// All clients that are running right now, ordered by pid.
+#[cfg(target_os = "linux")]
+pub fn running_clients() -> Vec<ClientProcess> {
+    let entries = match std::fs::read_dir("/proc") {
+        Ok(entries) => entries,
+        Err(_) => return vec![],
+    };
+    let mut clients = vec![];
+    for entry in entries.flatten() {
+        let pid = match entry.file_name().to_str().and_then(|pid| pid.parse().ok()) {
+            Some(pid) => pid,
+            None => continue,
+        };
+        let cmdline = match std::fs::read(entry.path().join("cmdline")) {
+            Ok(cmdline) => cmdline,
+            Err(_) => continue,
+        };
+        let args = cmdline
+            .split(|b| *b == 0)
+            .map(|arg| String::from_utf8_lossy(arg).to_string())
+            .collect::<Vec<_>>();
+
+        // the render processes have similar arguments, only the ux process itself counts
+        let is_client = args
+            .first()
+            .is_some_and(|exe| exe.to_lowercase().ends_with("leagueclientux.exe"));
+        if !is_client {
+            continue;
+        }
+
+        clients.extend(ClientProcess::from_args(pid, &args, wine_prefix(pid)));
+    }
+    clients.sort_by_key(|client| client.pid);
+    clients
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn running_clients() -> Vec<ClientProcess> {
+    use sysinfo::{ProcessExt, System, SystemExt};
+    // polled every few seconds, once is enough
+    static UNREADABLE: std::sync::Once = std::sync::Once::new();
+
+    let mut system = System::new();
+    system.refresh_processes();
+    let mut clients = vec![];
+    for (pid, process) in system.get_processes() {
+        if !["LeagueClientUx.exe", "LeagueClientUx"].contains(&process.name()) {
+            continue;
+        }
+        let pid = *pid as u32;
+        match ClientProcess::from_args(pid, process.cmd(), None) {
+            Some(client) => clients.push(client),
+            // e.g. a client started as administrator, its command line can't be read from here
+            None => UNREADABLE.call_once(|| {
+                log::warn!(
+                    target: logging::INTEROP,
+                    "client {} found but its command line has no port and password",
+                    pid
+                )
+            }),
+        }
+    }
+    clients.sort_by_key(|client| client.pid);
+    clients
+}
+
+/// The prefix is what tells clients apart when several accounts run at once.
+#[cfg(target_os = "linux")]
+fn wine_prefix(pid: u32) -> Option<String> {
+    let environ = std::fs::read(format!("/proc/{}/environ", pid)).ok()?;
+    environ
+        .split(|b| *b == 0)
+        .find_map(|var| var.strip_prefix(b"WINEPREFIX="))
+        .map(|prefix| String::from_utf8_lossy(prefix).to_string())
+}
+
+/// Polls for started and closed clients, sending `Event::Clients` whenever the list changes.
+pub fn watch(app: Sender<Event>, mut known: Vec<ClientProcess>) {
+    std::thread::spawn(move || loop {
+        std::thread::sleep(POLL_INTERVAL);
+        let clients = running_clients();
+        if clients != known {
+            known = clients.clone();
+            if app.send(Event::Clients(clients)).is_err() {
+                return;
+            }
+        }
+    });
+}
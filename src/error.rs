@@ -0,0 +1,54 @@
+//! Errors are `eyre::Report`s, every layer wraps what it was doing around the cause with `WrapErr`.
+//! `Error` is for the causes we produce ourselves, the rest are reported as they come.
+
+use std::fmt::{Debug, Display, Formatter};
+use std::time::Duration;
+
+pub use color_eyre::eyre::WrapErr;
+pub use color_eyre::Report;
+
+pub type Result<T, E = Report> = std::result::Result<T, E>;
+
+#[derive(Debug)]
+pub enum Error {
+    /// the client answered with an error or couldn't be reached
+    Lcu(String),
+    Timeout(Duration),
+    /// an event or response didn't have the shape we expected
+    Decode {
+        uri: String,
+        source: serde_json::Error,
+    },
+    Socket(String),
+}
+
+impl Error {
+    /// kassadin's errors don't implement `std::error::Error`, keep what they say.
+    pub fn lcu(e: impl Debug) -> Self {
+        Self::Lcu(format!("{:?}", e))
+    }
+
+    pub fn socket(e: impl Debug) -> Self {
+        Self::Socket(format!("{:?}", e))
+    }
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::Lcu(e) => write!(f, "client request failed: {}", e),
+            Error::Timeout(after) => write!(f, "client didn't answer within {:?}", after),
+            Error::Decode { uri, .. } => write!(f, "couldn't decode {}", uri),
+            Error::Socket(e) => write!(f, "websocket error: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Decode { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
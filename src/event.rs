@@ -1,11 +1,62 @@
-use kassadin::types::socket::LeagueEvent;
+use crate::command::{Command, CommandOutput};
+use crate::connection::{ConnectionStatus, Credentials};
+use crate::control::ControlAction;
+use crate::discovery::ClientProcess;
+use crate::error::Report;
+use crate::ui::account::ChatMe;
+use crate::ui::chat::{ChatMessage, Conversation};
+use crate::ui::game::champ_select::ChampSelectSession;
+use crate::ui::game::invitations::ReceivedInvitation;
+use crate::ui::game::lobby::LobbySession;
+use kassadin::client::LCU;
+use kassadin::types::socket::{EventType, LeagueEventKind};
+use serde_json::Value;
 
 pub enum Event {
-    CreateSocket(i32),
-    SocketCreated(i32),
+    CreateSocket(i32, Credentials),
     DeleteSocket(i32),
-    ReadSocket(i32),
-    LeagueEvent(i32, LeagueEvent),
-    LeagueEventEmpty(i32),
-    Ready,
+    LeagueEvent(i32, SocketEvent),
+    Connection(i32, ConnectionStatus),
+    /// the socket (re)connected, possibly to a restarted client with new credentials
+    Reconnected(i32, LCU),
+    /// instance, command id, the command and what came of it
+    CommandDone(i32, u64, Command, Result<CommandOutput, Report>),
+    /// the running clients changed
+    Clients(Vec<ClientProcess>),
+    /// a request from the control api, for the selected instance if `None`
+    Control(Option<i32>, ControlAction),
+    /// something failed outside of a command, wrapped in what we were doing at the time
+    Error(Report),
+}
+
+/// A websocket event after it went through the `Router`.
+#[derive(Debug)]
+pub struct SocketEvent {
+    pub uri: String,
+    pub event_type: Option<EventType>,
+    pub kind: EventKind,
+    /// set if `data` didn't decode, `kind` then carries `None`
+    pub error: Option<Report>,
+}
+
+#[derive(Debug)]
+pub enum EventKind {
+    /// boxed, kassadin's events are far bigger than ours
+    League(Box<LeagueEventKind>),
+    /// kassadin's `LeagueEventKind::ChampSelect` carries no data
+    ChampSelect(Option<ChampSelectSession>),
+    /// instead of kassadin's `LeagueEventKind::Lobby`, which lacks the queue
+    Lobby(Option<LobbySession>),
+    /// every invitation we received, answered ones included
+    Invitations(Option<Vec<ReceivedInvitation>>),
+    Me(Option<ChatMe>),
+    /// a friend group was added, changed or removed, the data isn't needed
+    FriendGroups,
+    Conversations(Option<Vec<Conversation>>),
+    /// by conversation id
+    Conversation(String, Option<Conversation>),
+    /// conversation id and the message
+    ChatMessage(String, Option<ChatMessage>),
+    /// no route matched, carries the raw `data`
+    Unknown(Value),
 }
@@ -0,0 +1,112 @@
+use serde_json::Value;
+use std::io::{Error, ErrorKind, Read, Write};
+use websocket::server::upgrade::Request as Head;
+use websocket::sync::server::upgrade::Buffer;
+
+/// Bodies announced past this are refused before anything is allocated.
+pub const MAX_BODY: usize = 64 * 1024;
+
+/// A plain HTTP request that arrived on a websocket server but wasn't an upgrade.
+#[derive(Debug, Clone)]
+pub struct Request {
+    pub method: String,
+    pub path: String,
+    pub query: Option<String>,
+    pub authorization: Option<String>,
+    /// as announced, `body` stays empty until `read_body`
+    pub content_length: usize,
+    pub body: Vec<u8>,
+}
+
+impl Request {
+    /// The request line and headers, enough to turn the request away before reading on.
+    pub fn parse(head: Head) -> Self {
+        let method = head.subject.0.to_string();
+        let uri = head.subject.1.to_string();
+        let (path, query) = match uri.split_once('?') {
+            Some((path, query)) => (path.to_string(), Some(query.to_string())),
+            None => (uri, None),
+        };
+
+        let header = |name: &str| {
+            head.headers
+                .get_raw(name)
+                .and_then(|raw| raw.first())
+                .map(|raw| String::from_utf8_lossy(raw).to_string())
+        };
+        let authorization = header("Authorization");
+        // unparsable counts as too large, not as empty
+        let content_length = header("Content-Length")
+            .map(|length| length.trim().parse::<usize>().unwrap_or(usize::MAX))
+            .unwrap_or_default();
+
+        Self {
+            method,
+            path,
+            query,
+            authorization,
+            content_length,
+            body: vec![],
+        }
+    }
+
+    /// Read the announced body, what `parse` already buffered first.
+    pub fn read_body<S: Read>(
+        &mut self,
+        stream: &mut S,
+        buffer: Option<Buffer>,
+    ) -> std::io::Result<()> {
+        let length = self.content_length;
+        if length > MAX_BODY {
+            return Err(Error::new(ErrorKind::InvalidData, "body too large"));
+        }
+        let mut body = match buffer {
+            Some(buffer) => buffer.buf[buffer.pos..buffer.cap].to_vec(),
+            None => vec![],
+        };
+        if body.len() < length {
+            let mut rest = vec![0; length - body.len()];
+            stream.read_exact(&mut rest)?;
+            body.extend(rest);
+        }
+        body.truncate(length);
+        self.body = body;
+        Ok(())
+    }
+
+    pub fn json(&self) -> Value {
+        serde_json::from_slice(&self.body).unwrap_or(Value::Null)
+    }
+}
+
+pub fn respond<S: Write>(stream: &mut S, status: u16, body: &Value) -> std::io::Result<()> {
+    let body = if body.is_null() && status == 204 {
+        String::new()
+    } else {
+        body.to_string()
+    };
+    write!(
+        stream,
+        "HTTP/1.1 {} {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        reason(status),
+        body.len(),
+        body
+    )?;
+    stream.flush()
+}
+
+fn reason(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        201 => "Created",
+        204 => "No Content",
+        400 => "Bad Request",
+        401 => "Unauthorized",
+        404 => "Not Found",
+        409 => "Conflict",
+        413 => "Payload Too Large",
+        500 => "Internal Server Error",
+        _ => "Unknown",
+    }
+}
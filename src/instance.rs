@@ -0,0 +1,218 @@
+use crate::command::CommandQueue;
+use crate::connection::{ConnectionStatus, Credentials};
+use crate::discovery::ClientProcess;
+use crate::error::{Result, WrapErr};
+use crate::event::Event;
+use crate::lcu;
+use crate::logging;
+use crate::ui::account::Account;
+use crate::ui::chat::Chat;
+use crate::ui::friendlist::Friendlist;
+use crate::ui::game::Game;
+use crate::ui::toasts::Toasts;
+use crossbeam::channel::Sender;
+use eframe::egui;
+use kassadin::client::LCU;
+
+/// One client with its own socket and state, the id doubles as socket index.
+pub struct Instance {
+    pub id: i32,
+    pub name: String,
+    /// set for clients found through `crate::discovery`
+    pub pid: Option<u32>,
+    /// whose settings to use, clients in the same Wine prefix share them and `None` shares the default ones
+    pub profile: Option<String>,
+    pub game: Game,
+    pub account: Account,
+    pub friendlist: Friendlist,
+    pub chat: Chat,
+    pub lcu: LCU,
+    pub connection: ConnectionStatus,
+    pub commands: CommandQueue,
+}
+
+impl Instance {
+    /// The confy name of this instance's `kind` settings, e.g. `clowncher/game`.
+    pub fn config_name(&self, kind: &str) -> String {
+        config_name(kind, self.profile.as_deref())
+    }
+
+    /// Store every setting of this instance.
+    pub fn save(&self) -> Result<()> {
+        self.game
+            .save(&self.config_name("game"))
+            .wrap_err("Save game settings")?;
+        self.friendlist
+            .save(&self.config_name("friends"))
+            .wrap_err("Save friend list settings")?;
+        Ok(())
+    }
+}
+
+fn config_name(kind: &str, profile: Option<&str>) -> String {
+    match profile {
+        None => format!("clowncher/{}", kind),
+        Some(profile) => {
+            let slug = profile
+                .chars()
+                .map(|c| {
+                    if c.is_ascii_alphanumeric() {
+                        c.to_ascii_lowercase()
+                    } else {
+                        '-'
+                    }
+                })
+                .collect::<String>();
+            format!("clowncher/{}-{}", kind, slug.trim_matches('-'))
+        }
+    }
+}
+
+pub struct Instances {
+    pub list: Vec<Instance>,
+    pub selected: Option<i32>,
+    next_id: i32,
+    interop: Sender<Event>,
+    results: Sender<Event>,
+}
+
+impl Instances {
+    pub fn new(interop: Sender<Event>, results: Sender<Event>) -> Self {
+        Self {
+            list: vec![],
+            selected: None,
+            next_id: 0,
+            interop,
+            results,
+        }
+    }
+
+    pub fn add(
+        &mut self,
+        name: impl Into<String>,
+        pid: Option<u32>,
+        profile: Option<String>,
+        credentials: Credentials,
+    ) -> i32 {
+        let id = self.next_id;
+        self.next_id += 1;
+
+        let name = name.into();
+        log::info!(target: logging::INTEROP, "instance {}: {}", id, name);
+        let lcu = credentials.read().unwrap_or_else(lcu::disconnected);
+        let config = |kind| config_name(kind, profile.as_deref());
+        self.list.push(Instance {
+            id,
+            name,
+            pid,
+            game: Game::load(&config("game")),
+            account: Default::default(),
+            friendlist: Friendlist::load(&config("friends")),
+            chat: Default::default(),
+            commands: CommandQueue::new(id, lcu.clone(), self.results.clone()),
+            lcu,
+            profile,
+            connection: Default::default(),
+        });
+        self.interop
+            .send(Event::CreateSocket(id, credentials))
+            .unwrap();
+
+        if self.selected.is_none() {
+            self.selected = Some(id);
+        }
+        id
+    }
+
+    /// Drop an instance, its settings are saved first.
+    pub fn remove(&mut self, id: i32) -> Result<()> {
+        log::info!(target: logging::INTEROP, "instance {} closed", id);
+        let saved = match self.list.iter().find(|instance| instance.id == id) {
+            Some(instance) => instance
+                .save()
+                .wrap_err_with(|| format!("Save settings of {}", instance.name)),
+            None => Ok(()),
+        };
+        self.list.retain(|instance| instance.id != id);
+        self.interop.send(Event::DeleteSocket(id)).unwrap();
+
+        if self.selected == Some(id) {
+            self.selected = self.list.first().map(|instance| instance.id);
+        }
+        saved
+    }
+
+    /// Adds instances for new clients and drops the ones whose client exited.
+    pub fn sync(&mut self, clients: Vec<ClientProcess>, toasts: &mut Toasts) {
+        let gone = self
+            .list
+            .iter()
+            .filter(|instance| {
+                instance
+                    .pid
+                    .is_some_and(|pid| !clients.iter().any(|client| client.pid == pid))
+            })
+            .map(|instance| instance.id)
+            .collect::<Vec<_>>();
+        for id in gone {
+            if let Err(e) = self.remove(id) {
+                toasts.push(e);
+            }
+        }
+
+        for client in clients {
+            if !self
+                .list
+                .iter()
+                .any(|instance| instance.pid == Some(client.pid))
+            {
+                let credentials = Credentials::Fixed(client.lcu());
+                self.add(client.label, Some(client.pid), client.prefix, credentials);
+            }
+        }
+    }
+
+    pub fn get_mut(&mut self, id: i32) -> Option<&mut Instance> {
+        self.list.iter_mut().find(|instance| instance.id == id)
+    }
+
+    pub fn selected(&self) -> Option<&Instance> {
+        let selected = self.selected?;
+        self.list.iter().find(|instance| instance.id == selected)
+    }
+
+    pub fn selected_mut(&mut self) -> Option<&mut Instance> {
+        let selected = self.selected?;
+        self.get_mut(selected)
+    }
+
+    pub fn ui(&mut self, ui: &mut egui::Ui) {
+        let Self { list, selected, .. } = self;
+
+        if list.is_empty() {
+            ui.label(egui::RichText::new("No client running").text_style(egui::TextStyle::Small));
+        }
+
+        for instance in list.iter() {
+            ui.horizontal(|ui| {
+                let (response, painter) =
+                    ui.allocate_painter(egui::Vec2::splat(8.0), egui::Sense::hover());
+                let rect = response.rect;
+                painter.circle_filled(
+                    rect.center(),
+                    rect.width() / 2.0,
+                    instance.connection.to_color(),
+                );
+
+                let is_selected = *selected == Some(instance.id);
+                if ui
+                    .selectable_label(is_selected, instance.name.as_str())
+                    .on_hover_text(instance.connection.label())
+                    .clicked()
+                {
+                    *selected = Some(instance.id);
+                }
+            });
+        }
+    }
+}
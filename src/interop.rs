@@ -1,258 +1,424 @@
-use crate::event::Event;
-use crate::ui::account::Account;
-use crate::ui::friendlist::Friendlist;
-use crate::ui::game::{Game, LobbyMember, SearchState};
+use crate::capture::{self, Capture, Recorder};
+use crate::command::{Command, CommandOutput};
+use crate::connection::{self, ConnectionStatus};
+use crate::control::ControlAction;
+use crate::error::Report;
+use crate::event::{Event, EventKind};
+use crate::instance::{Instance, Instances};
+use crate::logging;
+use crate::router::Router;
+use crate::ui::chat::{self, Chat};
+use crate::ui::game::champ_select::ChampSelect;
+use crate::ui::game::LobbyMember;
+use crate::ui::toasts::Toasts;
 use crossbeam::channel::{Receiver, Sender};
 use eframe::epi;
-use kassadin::client::{WebSocket, LCU};
-use kassadin::routes;
-
-use kassadin::types::socket::{EventType, GameFlowPhase, LeagueEvent, LeagueEventKind};
-use serde_json::Value;
+use kassadin::types::lcu::consts::Position;
+use kassadin::types::socket::{EventType, LeagueEventKind};
 use std::collections::HashMap;
-use std::sync::RwLock;
-use websocket::OwnedMessage;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Instant;
+
+/// Events handled per frame at most, so a burst can't stall the ui; the rest follow next frame.
+const MAX_BATCH: usize = 512;
 
-pub fn spawn_interop_thread(sender: Sender<Event>, receiver: Receiver<Event>, lcu: &LCU) {
-    let sockets = RwLock::new(HashMap::<i32, Receiver<LeagueEvent>>::new());
-    let lcu = lcu.clone();
+/// Creates and deletes sockets. Their threads push events straight to `sender`,
+/// nothing here waits on them.
+pub fn spawn_interop_thread(sender: Sender<Event>, receiver: Receiver<Event>, capture: Capture) {
+    let mut sockets = HashMap::<i32, Arc<AtomicBool>>::new();
+    let router = Arc::new(Router::league());
+    let recorder = match &capture {
+        Capture::Record(path) => match Recorder::create(path) {
+            Ok(recorder) => Some(recorder),
+            Err(e) => {
+                let action = format!("Record session to {}", path.display());
+                sender
+                    .send(Event::Error(Report::new(e).wrap_err(action)))
+                    .unwrap();
+                None
+            }
+        },
+        _ => None,
+    };
     std::thread::spawn(move || {
         while let Ok(event) = receiver.recv() {
             match event {
-                Event::CreateSocket(idx) => {
-                    let websocket = { lcu.socket().build() };
-
-                    let (send, recv) = crossbeam::channel::unbounded();
-                    {
-                        let mut s = sockets.write().unwrap();
-                        s.insert(idx, recv);
-                    }
-                    std::thread::spawn(move || {
-                        handle_socket_thread(websocket, send);
-                    });
-
-                    sender.send(Event::SocketCreated(idx)).unwrap();
-                }
-                Event::ReadSocket(idx) => {
-                    let sockets = sockets.read().unwrap();
-                    let events = sockets.get(&idx).unwrap();
-                    if let Ok(event) = events.recv() {
-                        sender.send(Event::LeagueEvent(idx, event)).unwrap();
-                    }
-                }
-                _ => {}
-            }
-            sender.send(Event::Ready).unwrap();
-        }
-    });
-}
-
-fn handle_socket_thread(mut socket: WebSocket, send: Sender<LeagueEvent>) {
-    socket.start().unwrap();
-    for message in socket.client.incoming_messages() {
-        let message = message.unwrap();
-        match message {
-            OwnedMessage::Text(message) => {
-                if let Ok(val) = serde_json::from_str::<Value>(&message) {
-                    let mut event = match serde_json::from_value::<
-                        kassadin::types::socket::LeagueEvent,
-                    >(val[2].clone())
-                    {
-                        Ok(event) => event,
-                        Err(_) => LeagueEvent::default(),
-                    };
+                Event::CreateSocket(idx, credentials) => {
+                    let stop = Arc::new(AtomicBool::new(false));
+                    sockets.insert(idx, stop.clone());
+                    log::debug!(target: logging::INTEROP, "created socket {}", idx);
 
-                    if val[2].get("uri").is_some() {
-                        let uri = val[2]["uri"].as_str().unwrap();
-                        match uri {
-                            routes::matchmaking::SEARCH
-                            | routes::lobby::team_builder::MATCHMAKING => {
-                                event.kind = Some(LeagueEventKind::Queue(None));
-                                match serde_json::from_value::<kassadin::types::socket::QueueEvent>(
-                                    val[2]["data"].clone(),
-                                ) {
-                                    Ok(kind) => {
-                                        event.kind = Some(LeagueEventKind::Queue(Some(kind)))
+                    match &capture {
+                        Capture::Replay { path, speed } => {
+                            let (path, speed) = (path.clone(), *speed);
+                            let router = router.clone();
+                            let app = sender.clone();
+                            std::thread::spawn(move || {
+                                let _ =
+                                    app.send(Event::Connection(idx, ConnectionStatus::Connected));
+                                let replayed = capture::replay(&path, idx, speed, |message| {
+                                    if stop.load(Ordering::Relaxed) {
+                                        return false;
                                     }
-                                    Err(_) => {}
-                                }
-                                if uri == routes::lobby::team_builder::MATCHMAKING {}
-                                send.send(event).unwrap();
-                            }
-                            routes::game_flow::SESSION => {
-                                event.kind = Some(LeagueEventKind::GameFlow(None));
-                                match serde_json::from_value::<kassadin::types::socket::GameFlowEvent>(
-                                    val[2]["data"].clone(),
-                                ) {
-                                    Ok(kind) => {
-                                        event.kind = Some(LeagueEventKind::GameFlow(Some(kind)))
-                                    }
-                                    Err(e) => {
-                                        println!("error: {:?}", e);
-                                    }
-                                }
-                                send.send(event).unwrap();
-                            }
-                            routes::lobby::LOBBY => {
-                                event.kind = Some(LeagueEventKind::Lobby(None));
-                                match serde_json::from_value::<kassadin::types::socket::LobbyEvent>(
-                                    val[2]["data"].clone(),
-                                ) {
-                                    Ok(kind) => {
-                                        event.kind = Some(LeagueEventKind::Lobby(Some(kind)))
-                                    }
-                                    Err(e) => {
-                                        println!("error: {:?}", e);
-                                    }
-                                }
-                                send.send(event).unwrap();
-                            }
-                            // match things that require if 😔
-                            _ => {
-                                if uri.contains(routes::chat::FRIENDS) {
-                                    event.kind = Some(LeagueEventKind::Friend(None));
-                                    match serde_json::from_value::<
-                                        kassadin::types::socket::FriendEvent,
-                                    >(
-                                        val[2]["data"].clone()
-                                    ) {
-                                        Ok(kind) => {
-                                            event.kind = Some(LeagueEventKind::Friend(Some(kind)))
-                                        }
-                                        Err(e) => {
-                                            println!("error: {:?}", e);
+                                    match router.dispatch(message) {
+                                        Some(event) => {
+                                            app.send(Event::LeagueEvent(idx, event)).is_ok()
                                         }
+                                        None => true,
                                     }
-                                    send.send(event).unwrap();
+                                });
+                                if let Err(e) = replayed {
+                                    let action = format!("Replay session {}", path.display());
+                                    let _ = app.send(Event::Error(Report::new(e).wrap_err(action)));
                                 }
-                            }
+                                let _ = app.send(Event::Connection(idx, ConnectionStatus::Closed));
+                            });
                         }
+                        _ => {
+                            let router = router.clone();
+                            let recorder = recorder.clone();
+                            let app = sender.clone();
+                            std::thread::spawn(move || {
+                                connection::run(idx, credentials, router, recorder, app, stop);
+                            });
+                        }
+                    }
+                }
+                Event::DeleteSocket(idx) => {
+                    if let Some(stop) = sockets.remove(&idx) {
+                        stop.store(true, Ordering::Relaxed);
                     }
                 }
+                _ => {}
             }
-            _ => {}
         }
-    }
+    });
 }
 
+/// Handles what arrived since the last frame, from every socket at once.
 pub fn match_events(
     receiver: &mut Receiver<Event>,
-    sender: &mut Sender<Event>,
     ctx: &egui::CtxRef,
     _frame: &epi::Frame,
-    lcu: &LCU,
-    game: &mut Game,
-    _account: &mut Account,
-    friendlist: &mut Friendlist,
+    instances: &mut Instances,
+    toasts: &mut Toasts,
 ) {
-    while let Ok(event) = receiver.recv_deadline(std::time::Instant::now()) {
+    for event in receiver.try_iter().take(MAX_BATCH) {
         match event {
-            Event::Ready => {
-                sender.send(Event::ReadSocket(0)).unwrap();
+            Event::LeagueEvent(idx, event) => {
+                if let Some(instance) = instances.get_mut(idx) {
+                    match_league_event(instance, event.event_type, event.kind, &event.uri);
+                }
+                ctx.request_repaint();
             }
-            Event::SocketCreated(idx) => {
-                println!("Created Socket: {}", idx);
-                sender.send(Event::ReadSocket(idx)).unwrap();
+            Event::Connection(idx, status) => {
+                if let Some(instance) = instances.get_mut(idx) {
+                    if instance.connection == ConnectionStatus::Connected
+                        && status != ConnectionStatus::Connected
+                    {
+                        // the client exited or dropped us, whatever it told us is stale now
+                        log::info!(target: logging::SOCKET, "socket {} lost its client", idx);
+                        instance.game.reset();
+                        instance.account = Default::default();
+                        instance.friendlist.clear();
+                        instance.chat.clear();
+                    }
+                    instance.connection = status;
+                }
+                ctx.request_repaint();
             }
-            Event::LeagueEvent(idx, event) => {
-                match event.kind {
-                    None => panic!("this shouldn't be the case lol"),
-                    Some(kind) => match kind {
-                        LeagueEventKind::Queue(queue_event) => {
-                            if event.event_type.is_some() {
-                                match event.event_type.unwrap() {
-                                    EventType::Update => {
-                                        if let Some(queue_event) = queue_event {
-                                            game.queue_timer = queue_event.time_in_queue;
-                                            game.estimated_queue_time =
-                                                queue_event.estimated_queue_time;
-                                        }
-                                    }
-                                    EventType::Delete => {
-                                        game.estimated_queue_time = None;
-                                        game.queue_timer = None;
-                                    }
-                                    _ => {}
-                                }
-                            }
-                        }
-                        LeagueEventKind::Lobby(lobby) => {
-                            if let Some(lobby) = lobby {
-                                if let Some(members) = &lobby.members {
-                                    let mut m = vec![];
-                                    for member in members {
-                                        if let Some(puuid) = &member.puuid {
-                                            match crate::RT
-                                                .block_on(async { lcu.ranked().stats(puuid).await })
-                                            {
-                                                Ok(status) => {
-                                                    m.push(LobbyMember::from(
-                                                        member.clone(),
-                                                        status,
-                                                    ));
-                                                }
-                                                Err(e) => {
-                                                    println!("lobby {:?}", e)
-                                                }
-                                            }
-                                        }
-                                    }
-                                    game.update_members(m);
-                                }
-                            }
+            Event::Reconnected(idx, client) => {
+                log::info!(target: logging::SOCKET, "socket {} connected", idx);
+                if let Some(instance) = instances.get_mut(idx) {
+                    instance.lcu = client;
+                    instance.commands.set_lcu(instance.lcu.clone());
+                    instance.game.reset();
+                    instance.chat.clear();
+                    instance.commands.send(Command::LoadFriends);
+                    instance.commands.send(Command::LoadFriendGroups);
+                    instance.commands.send(Command::LoadQueues);
+                }
+                ctx.request_repaint();
+            }
+            Event::Clients(clients) => {
+                instances.sync(clients, toasts);
+                ctx.request_repaint();
+            }
+            Event::Control(idx, action) => {
+                let instance = match idx {
+                    Some(idx) => instances.get_mut(idx),
+                    None => instances.selected_mut(),
+                };
+                if let Some(instance) = instance {
+                    match_control(instance, action);
+                }
+                ctx.request_repaint();
+            }
+            Event::Error(e) => {
+                toasts.push(e);
+                ctx.request_repaint();
+            }
+            Event::CommandDone(idx, id, command, result) => {
+                let instance = match instances.get_mut(idx) {
+                    Some(instance) => instance,
+                    // the client exited while the command ran
+                    None => continue,
+                };
+                instance.commands.finish(id);
+                match result {
+                    Ok(CommandOutput::Done) => {}
+                    Ok(CommandOutput::RankedStats(puuid, status)) => {
+                        instance.game.update_ranked(&puuid, status);
+                    }
+                    Ok(CommandOutput::Friends(friends)) => {
+                        instance.friendlist.set_friends(friends);
+                    }
+                    Ok(CommandOutput::FriendGroups(groups, members)) => {
+                        instance.friendlist.set_groups(groups, members);
+                    }
+                    Ok(CommandOutput::Queues(queues, eligibility)) => {
+                        instance.game.queues.set(queues, eligibility);
+                    }
+                    Ok(CommandOutput::Conversation(id, messages, participants)) => {
+                        instance.chat.history(&id, messages, participants);
+                    }
+                    Err(e) => match &command {
+                        // the row says so, no need for a toast per player
+                        Command::RankedStats(puuid) => {
+                            instance.game.ranked.failed(puuid, Instant::now());
+                            log::warn!(target: logging::LOBBY, "ranked lookup failed: {}", e);
                         }
-                        LeagueEventKind::ChampSelect => {}
-                        LeagueEventKind::GameFlow(flow_event) => {
-                            if let Some(flow_event) = flow_event {
-                                if let Some(phase) = flow_event.phase {
-                                    match phase {
-                                        GameFlowPhase::Lobby => {
-                                            game.search_sate = SearchState::Lobby;
-                                        }
-                                        GameFlowPhase::Matchmaking => {
-                                            game.search_sate = SearchState::Searching;
-                                        }
-                                        GameFlowPhase::ReadyCheck => {
-                                            game.search_sate = SearchState::Found;
-                                        }
-                                        GameFlowPhase::ChampSelect => {
-                                            game.search_sate = SearchState::ChampSelect;
-                                        }
-                                        GameFlowPhase::GameStart => {
-                                            game.search_sate = SearchState::InGame;
-                                        }
-                                        GameFlowPhase::InProgress => {
-                                            game.search_sate = SearchState::InGame;
-                                        }
-                                        GameFlowPhase::PreEndOfGame => {}
-                                        GameFlowPhase::EndOfGame => {}
-                                        GameFlowPhase::WaitingForStats => {
-                                            game.search_sate = SearchState::AfterGameLobby;
-                                        }
-                                        GameFlowPhase::TerminatedInError => {
-                                            game.search_sate = SearchState::Error;
-                                        }
-                                        GameFlowPhase::None => {
-                                            game.search_sate = SearchState::None;
-                                        }
-                                    }
+                        _ => {
+                            match command {
+                                Command::JoinLobby(..) => instance.game.queue_id = None,
+                                // the friend was already shown in the group it was dropped on
+                                Command::MoveFriend(..) => {
+                                    instance.commands.send(Command::LoadFriendGroups);
                                 }
+                                _ => {}
                             }
-                        }
-                        LeagueEventKind::Friend(friend) => {
-                            if let Some(friend) = friend {
-                                friendlist.update(friend);
-                            }
+                            toasts.push(e);
                         }
                     },
                 }
                 ctx.request_repaint();
-                sender.send(Event::ReadSocket(idx)).unwrap();
-            }
-            Event::LeagueEventEmpty(idx) => {
-                sender.send(Event::ReadSocket(idx)).unwrap();
             }
             _ => {}
         }
     }
+
+    if !receiver.is_empty() {
+        ctx.request_repaint();
+    }
+
+    // not only the selected instance should accept its matches
+    for instance in &mut instances.list {
+        instance.game.auto_accept(&mut instance.commands);
+    }
+}
+
+fn match_control(instance: &mut Instance, action: ControlAction) {
+    match action {
+        ControlAction::Command(command) => {
+            instance.commands.send(command);
+        }
+        ControlAction::SetRoles(positions) => {
+            instance.game.positions = positions.clone();
+            instance.commands.send(Command::SetRoles(positions));
+        }
+        ControlAction::Invite(summoner_id) => {
+            let name = instance
+                .friendlist
+                .friends
+                .iter()
+                .find(|friend| friend.summoner_id == summoner_id)
+                .map_or_else(|| summoner_id.to_string(), |friend| friend.name.clone());
+            instance
+                .game
+                .invite(&mut instance.commands, summoner_id, &name);
+        }
+        ControlAction::InviteFriend(name) => {
+            let friend = instance
+                .friendlist
+                .friends
+                .iter()
+                .find(|friend| friend.name == name || friend.riot_name == name);
+            match friend {
+                Some(friend) => {
+                    let (summoner_id, name) = (friend.summoner_id, friend.name.clone());
+                    instance
+                        .game
+                        .invite(&mut instance.commands, summoner_id, &name);
+                }
+                None => log::warn!(target: logging::INTEROP, "control: no friend named {}", name),
+            }
+        }
+    }
+}
+
+fn match_league_event(
+    instance: &mut Instance,
+    event_type: Option<EventType>,
+    kind: EventKind,
+    uri: &str,
+) {
+    let Instance {
+        game,
+        account,
+        commands,
+        friendlist,
+        chat,
+        ..
+    } = instance;
+
+    match kind {
+        EventKind::Unknown(_) => {
+            log::trace!(target: logging::SOCKET, "unrouted event: {}", uri);
+        }
+        EventKind::Me(me) => {
+            if let Some(me) = me {
+                account.update(me);
+            }
+        }
+        EventKind::FriendGroups => {
+            // our own group commands reload once they are done
+            let loading = commands.is_pending(|c| {
+                matches!(
+                    c,
+                    Command::LoadFriendGroups
+                        | Command::CreateFriendGroup(_)
+                        | Command::RenameFriendGroup(..)
+                        | Command::DeleteFriendGroup(_)
+                        | Command::MoveFriend(..)
+                )
+            });
+            if !loading {
+                commands.send(Command::LoadFriendGroups);
+            }
+        }
+        EventKind::ChampSelect(session) => {
+            game.champ_select = ChampSelect::apply(game.champ_select.take(), event_type, session);
+        }
+        EventKind::Lobby(lobby) => match (event_type, lobby) {
+            (Some(EventType::Delete), _) => {
+                game.queue_id = None;
+                game.invitations.clear();
+                game.update_members(vec![], commands);
+            }
+            (_, Some(lobby)) => {
+                // also picks up lobbies made in the official client
+                game.queue_id = lobby.queue_id();
+                if game.queue_id.is_some() {
+                    game.last_queue_id = game.queue_id;
+                }
+                if let Some(positions) = lobby.positions() {
+                    game.positions = positions;
+                }
+                game.invitations = lobby.invitations;
+                let members = lobby
+                    .members
+                    .into_iter()
+                    .filter(|member| member.puuid.is_some())
+                    .map(LobbyMember::from)
+                    .collect::<Vec<_>>();
+                note_role_changes(chat, &game.members, &members);
+                game.update_members(members, commands);
+            }
+            (_, None) => {}
+        },
+        EventKind::Conversations(conversations) => {
+            if let Some(conversations) = conversations {
+                for id in chat.set_conversations(conversations) {
+                    commands.send(Command::LoadConversation(id));
+                }
+            }
+        }
+        EventKind::Conversation(id, conversation) => match (event_type, conversation) {
+            (Some(EventType::Delete), _) => chat.remove(&id),
+            (_, Some(conversation)) => {
+                if chat.conversation(conversation) {
+                    commands.send(Command::LoadConversation(id));
+                }
+            }
+            (_, None) => {}
+        },
+        EventKind::ChatMessage(id, message) => {
+            if let Some(message) = message {
+                // someone joined we have no name for, the participants know it
+                let stranger =
+                    message.is_system() && chat.is_stranger(&id, message.from_summoner_id);
+                if chat.message(&id, message) && stranger {
+                    commands.send(Command::LoadConversation(id));
+                }
+            }
+        }
+        EventKind::Invitations(invitations) => match (event_type, invitations) {
+            (Some(EventType::Delete), _) => game.inbox.clear(),
+            (_, Some(invitations)) => game.inbox.set(invitations),
+            (_, None) => {}
+        },
+        EventKind::League(kind) => match *kind {
+            LeagueEventKind::Queue(queue_event) => match event_type {
+                Some(EventType::Update) => {
+                    if let Some(queue_event) = queue_event {
+                        game.queue_timer = queue_event.time_in_queue;
+                        game.queue_timer_updated = Some(Instant::now());
+                        game.estimated_queue_time = queue_event.estimated_queue_time;
+                    }
+                }
+                Some(EventType::Delete) => {
+                    game.estimated_queue_time = None;
+                    game.queue_timer = None;
+                    game.queue_timer_updated = None;
+                }
+                _ => {}
+            },
+            // routed to our own models instead
+            LeagueEventKind::Lobby(_) | LeagueEventKind::ChampSelect => {}
+            LeagueEventKind::GameFlow(flow_event) => {
+                if let Some(phase) = flow_event.and_then(|flow_event| flow_event.phase) {
+                    game.gameflow(&phase, account);
+                }
+            }
+            LeagueEventKind::Friend(friend) => {
+                if let Some(friend) = friend {
+                    friendlist.update(friend);
+                }
+            }
+        },
+    }
+}
+
+/// The chat doesn't announce role changes, the lobby room gets a line of our own for them.
+fn note_role_changes(chat: &mut Chat, old: &[LobbyMember], new: &[LobbyMember]) {
+    chat.learn_names(
+        chat::LOBBY,
+        new.iter()
+            .map(|member| (member.summoner_id, member.name.clone())),
+    );
+    for member in new {
+        let before = match old.iter().find(|old| old.summoner_id == member.summoner_id) {
+            Some(before) => &before.positions,
+            None => continue,
+        };
+        let after = &member.positions;
+        if before.first_preference == after.first_preference
+            && before.second_preference == after.second_preference
+        {
+            continue;
+        }
+        let roles = [after.first_preference, after.second_preference]
+            .iter()
+            .flatten()
+            .filter(|position| **position != Position::UNSELECTED)
+            .map(|position| position.to_string().to_lowercase())
+            .collect::<Vec<_>>();
+        let text = if roles.is_empty() {
+            format!("{} cleared their roles", member.name)
+        } else {
+            format!("{} now plays {}", member.name, roles.join(" and "))
+        };
+        chat.note(chat::LOBBY, text);
+    }
 }
@@ -0,0 +1,157 @@
+//! What kassadin's `LCU` leaves out: clients from known credentials instead of the one process
+//! it looks for, e.g. `crate::mock` or a client found by `crate::discovery`,
+//! requests to any uri and a socket that fails instead of panicking.
+//! Its own request methods are private.
+
+use crate::discovery;
+use crate::error::{Error, Result, WrapErr};
+use kassadin::client::{ClientInfo, WebSocket, LCU};
+use kassadin::types::consts::Region;
+use reqwest::header::{HeaderMap, HeaderValue, AUTHORIZATION};
+use reqwest::{ClientBuilder, Method};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::Value;
+use websocket::header::{Authorization, Headers};
+
+/// A client listening on `port` that takes `password`, the way its command line passes them.
+pub fn from_credentials(port: u16, password: &str) -> LCU {
+    let mut headers = HeaderMap::new();
+    if let Ok(value) = HeaderValue::from_str(&basic_auth(password)) {
+        headers.insert(AUTHORIZATION, value);
+    }
+    LCU {
+        info: Some(ClientInfo {
+            pid: 0,
+            path: String::new(),
+            token: password.to_string(),
+            port: port.to_string(),
+            // the command line knows, nothing we call reads it
+            region: Region::EUW,
+            locale: String::new(),
+        }),
+        // the client's certificate is self signed
+        requester: ClientBuilder::new()
+            .danger_accept_invalid_certs(true)
+            .default_headers(headers)
+            .build()
+            .unwrap(),
+    }
+}
+
+/// A socket subscribed to every client event. kassadin's `socket().build()` panics
+/// whenever the client can't be reached, which is routine while it restarts.
+pub fn connect(lcu: &LCU) -> Result<WebSocket> {
+    let info = lcu
+        .info
+        .as_ref()
+        .ok_or_else(|| Error::Socket("no client".to_string()))?;
+    let mut headers = Headers::new();
+    headers.set(Authorization(basic_auth(&info.token)));
+    let tls = native_tls::TlsConnector::builder()
+        .danger_accept_invalid_hostnames(true)
+        .danger_accept_invalid_certs(true)
+        .build()?;
+    let url = format!("wss://127.0.0.1:{}", info.port);
+    let client = websocket::ClientBuilder::new(&url)?
+        .custom_headers(&headers)
+        .connect_secure(Some(tls))
+        .wrap_err_with(|| format!("connect to {}", url))?;
+    let mut socket = WebSocket {
+        client,
+        lcu: lcu.clone(),
+    };
+    socket.start().wrap_err("subscribe to client events")?;
+    Ok(socket)
+}
+
+/// The first client `crate::discovery` finds. `LCU::new` would panic while none is running.
+pub fn find() -> Option<LCU> {
+    discovery::running_clients()
+        .first()
+        .map(|client| client.lcu())
+}
+
+/// Stands in for a client that isn't there yet, every request fails until it's replaced.
+pub fn disconnected() -> LCU {
+    LCU {
+        info: None,
+        requester: reqwest::Client::new(),
+    }
+}
+
+/// `Authorization` for the client's user `riot`.
+pub fn basic_auth(password: &str) -> String {
+    format!("Basic {}", base64::encode(format!("riot:{}", password)))
+}
+
+pub async fn get<T: DeserializeOwned>(lcu: &LCU, uri: &str) -> Result<T> {
+    request(lcu, Method::GET, uri, None::<&()>).await
+}
+
+pub async fn post<T: DeserializeOwned>(lcu: &LCU, uri: &str, body: &impl Serialize) -> Result<T> {
+    request(lcu, Method::POST, uri, Some(body)).await
+}
+
+/// `POST uri` without a body, for actions like accepting a ready check.
+pub async fn post_empty(lcu: &LCU, uri: &str) -> Result<Value> {
+    request(lcu, Method::POST, uri, None::<&()>).await
+}
+
+pub async fn put<T: DeserializeOwned>(lcu: &LCU, uri: &str, body: &impl Serialize) -> Result<T> {
+    request(lcu, Method::PUT, uri, Some(body)).await
+}
+
+pub async fn delete<T: DeserializeOwned>(lcu: &LCU, uri: &str) -> Result<T> {
+    request(lcu, Method::DELETE, uri, None::<&()>).await
+}
+
+/// `method uri` with `body` as json. Error statuses fail with what the client said,
+/// empty answers like `204 No Content` decode as `null`.
+async fn request<T: DeserializeOwned, B: Serialize>(
+    lcu: &LCU,
+    method: Method,
+    uri: &str,
+    body: Option<&B>,
+) -> Result<T> {
+    send(lcu, method.clone(), uri, body)
+        .await
+        .wrap_err_with(|| format!("{} {}", method, uri))
+}
+
+async fn send<T: DeserializeOwned, B: Serialize>(
+    lcu: &LCU,
+    method: Method,
+    uri: &str,
+    body: Option<&B>,
+) -> Result<T> {
+    let info = lcu
+        .info
+        .as_ref()
+        .ok_or_else(|| Error::Lcu("no client".to_string()))?;
+    let url = format!("https://127.0.0.1:{}{}", info.port, uri);
+    let mut request = lcu.requester.request(method, &url);
+    if let Some(body) = body {
+        request = request.json(body);
+    }
+    let response = request.send().await?;
+    let status = response.status();
+    let bytes = response.bytes().await?;
+    if !status.is_success() {
+        let message = serde_json::from_slice::<Value>(&bytes)
+            .ok()
+            .and_then(|body| body["message"].as_str().map(str::to_string))
+            .unwrap_or_else(|| String::from_utf8_lossy(&bytes).to_string());
+        return Err(Error::Lcu(format!("{} {}", status, message)).into());
+    }
+    let bytes = if bytes.is_empty() {
+        &b"null"[..]
+    } else {
+        &bytes[..]
+    };
+    let data = serde_json::from_slice(bytes).map_err(|source| Error::Decode {
+        uri: uri.to_string(),
+        source,
+    })?;
+    Ok(data)
+}
@@ -2,8 +2,22 @@
 extern crate lazy_static;
 
 mod app;
+pub mod capture;
+pub mod command;
+pub mod connection;
+pub mod control;
+pub mod discovery;
+pub mod error;
 pub mod event;
+mod http;
+mod instance;
 mod interop;
+pub mod lcu;
+pub mod logging;
+pub mod mock;
+pub mod queues;
+pub mod repaint;
+pub mod router;
 mod widgets;
 mod ui;
 
@@ -42,12 +56,12 @@ impl TextureManager {
     fn draw_image(&self, ui: &mut egui::Ui, name: &str, size: Option<egui::Vec2>) {
         let (s, id) = self.images.get(name).unwrap();
         match size {
-            None => ui.image(id.clone(), s.clone()),
-            Some(size) => ui.image(id.clone(), size),
+            None => ui.image(*id, *s),
+            Some(size) => ui.image(*id, size),
         };
     }
 
     fn get_texture_id(&self, name: &str) -> (egui::Vec2, egui::TextureId) {
-        self.images.get(name).unwrap().clone()
+        *self.images.get(name).unwrap()
     }
 }
@@ -0,0 +1,177 @@
+//! Logs go to `clowncher.log` in the local data directory, rotated once it gets big,
+//! and the most recent ones are kept in memory for the log panel.
+//! `RUST_LOG` sets the level, `--log-stdout` also prints every line.
+//!
+//! Use the `log` macros with one of the targets below, e.g.
+//! `log::info!(target: logging::LOBBY, "joined {:?}", queue_id)`.
+
+use chrono::{DateTime, Local};
+use directories::ProjectDirs;
+use log::{Level, LevelFilter, Log, Metadata, Record};
+use std::collections::VecDeque;
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+
+pub const SOCKET: &str = "socket";
+pub const INTEROP: &str = "interop";
+pub const LOBBY: &str = "lobby";
+pub const FRIENDS: &str = "friends";
+pub const UI: &str = "ui";
+pub const MOCK: &str = "mock";
+
+/// The targets the log panel offers as filters.
+pub const TARGETS: [&str; 6] = [SOCKET, INTEROP, LOBBY, FRIENDS, UI, MOCK];
+
+const MAX_FILE_SIZE: u64 = 1024 * 1024;
+/// rotated files kept besides the current one, `clowncher.log.1` is the newest
+const MAX_FILES: usize = 3;
+const MAX_ENTRIES: usize = 2000;
+
+#[derive(Debug, Clone)]
+pub struct Entry {
+    pub time: DateTime<Local>,
+    pub level: Level,
+    pub target: String,
+    pub message: String,
+}
+
+struct RotatingFile {
+    path: PathBuf,
+    file: File,
+    size: u64,
+}
+
+impl RotatingFile {
+    fn open(path: PathBuf) -> std::io::Result<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(&path)?;
+        let size = file.metadata()?.len();
+        Ok(Self { path, file, size })
+    }
+
+    fn write(&mut self, line: &str) -> std::io::Result<()> {
+        if self.size + line.len() as u64 > MAX_FILE_SIZE {
+            self.rotate()?;
+        }
+        self.file.write_all(line.as_bytes())?;
+        self.size += line.len() as u64;
+        Ok(())
+    }
+
+    fn rotate(&mut self) -> std::io::Result<()> {
+        for i in (1..MAX_FILES).rev() {
+            let from = rotated(&self.path, i);
+            if from.exists() {
+                std::fs::rename(from, rotated(&self.path, i + 1))?;
+            }
+        }
+        std::fs::rename(&self.path, rotated(&self.path, 1))?;
+        *self = Self::open(self.path.clone())?;
+        Ok(())
+    }
+}
+
+fn rotated(path: &Path, i: usize) -> PathBuf {
+    let mut path = path.as_os_str().to_owned();
+    path.push(format!(".{}", i));
+    path.into()
+}
+
+struct Logger {
+    file: Mutex<Option<RotatingFile>>,
+    entries: Mutex<VecDeque<Entry>>,
+    stdout: AtomicBool,
+}
+
+impl Log for Logger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        // dependencies are only interesting when something is really wrong
+        metadata.target().starts_with(env!("CARGO_PKG_NAME"))
+            || TARGETS.contains(&metadata.target())
+            || metadata.level() <= Level::Warn
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        let entry = Entry {
+            time: Local::now(),
+            level: record.level(),
+            target: record.target().to_string(),
+            message: record.args().to_string(),
+        };
+
+        let line = format!(
+            "{} {:<5} [{}] {}\n",
+            entry.time.format("%Y-%m-%d %H:%M:%S%.3f"),
+            entry.level,
+            entry.target,
+            entry.message
+        );
+        if self.stdout.load(Ordering::Relaxed) {
+            print!("{}", line);
+        }
+        if let Some(file) = &mut *self.file.lock().unwrap() {
+            // nowhere left to report this to
+            let _ = file.write(&line);
+        }
+
+        let mut entries = self.entries.lock().unwrap();
+        entries.push_back(entry);
+        if entries.len() > MAX_ENTRIES {
+            entries.pop_front();
+        }
+    }
+
+    fn flush(&self) {
+        if let Some(file) = &mut *self.file.lock().unwrap() {
+            let _ = file.file.flush();
+        }
+    }
+}
+
+lazy_static! {
+    static ref LOGGER: Logger = Logger {
+        file: Mutex::new(None),
+        entries: Mutex::new(VecDeque::new()),
+        stdout: AtomicBool::new(false),
+    };
+}
+
+/// Where the log file goes, the platform's local data directory.
+pub fn log_path() -> crate::error::Result<PathBuf> {
+    let project = ProjectDirs::from("rs", "", "clowncher")
+        .ok_or_else(|| std::io::Error::new(std::io::ErrorKind::NotFound, "no home directory"))?;
+    Ok(project.data_local_dir().join("clowncher.log"))
+}
+
+/// The level `RUST_LOG` asks for, e.g. `RUST_LOG=debug`, `Info` if it's unset or no level.
+pub fn level_from_env() -> LevelFilter {
+    std::env::var("RUST_LOG")
+        .ok()
+        .and_then(|level| level.parse().ok())
+        .unwrap_or(LevelFilter::Info)
+}
+
+/// Install the logger, without a log file if it can't be opened.
+/// With `stdout` every line is printed as well.
+pub fn init(level: LevelFilter, stdout: bool) -> crate::error::Result<()> {
+    log::set_logger(&*LOGGER).expect("logger installed twice");
+    log::set_max_level(level);
+    LOGGER.stdout.store(stdout, Ordering::Relaxed);
+
+    let path = log_path()?;
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    *LOGGER.file.lock().unwrap() = Some(RotatingFile::open(path)?);
+    Ok(())
+}
+
+/// Calls `f` with the entries kept in memory, oldest first.
+pub fn with_entries<T>(f: impl FnOnce(&VecDeque<Entry>) -> T) -> T {
+    f(&LOGGER.entries.lock().unwrap())
+}
@@ -1,9 +1,50 @@
 // TODO: Add Browser Support
 // TODO: maybe consider a bridge to make the launcher work on mobile 🤔
 
+use clowncher::capture::Capture;
+use clowncher::connection::Credentials;
+
 #[cfg(not(target_arch = "wasm32"))]
-fn main() {
-    let app = clowncher::App::default();
+fn main() -> color_eyre::Result<()> {
+    color_eyre::install()?;
+    let level = clowncher::logging::level_from_env();
+    if let Err(e) = clowncher::logging::init(level, has_flag("--log-stdout")) {
+        log::warn!("no log file: {}", e);
+    }
+
+    let capture = if let Some(path) = arg_value("--record") {
+        Capture::Record(path.into())
+    } else if let Some(path) = arg_value("--replay") {
+        let speed = arg_value("--replay-speed")
+            .and_then(|speed| speed.parse().ok())
+            .unwrap_or(1.0);
+        Capture::Replay {
+            path: path.into(),
+            speed,
+        }
+    } else {
+        Capture::None
+    };
+
+    let credentials = if has_flag("--mock") {
+        let mock = clowncher::mock::spawn(Default::default());
+        log::info!(target: clowncher::logging::MOCK, "listening on port {}", mock.port);
+        Credentials::Fixed(mock.lcu())
+    } else {
+        Credentials::Lockfile
+    };
+
+    let app = clowncher::App::new(credentials, capture);
     let native_options = eframe::NativeOptions::default();
     eframe::run_native(Box::new(app), native_options);
 }
+
+fn has_flag(flag: &str) -> bool {
+    std::env::args().any(|arg| arg == flag)
+}
+
+fn arg_value(flag: &str) -> Option<String> {
+    let mut args = std::env::args().skip_while(|arg| arg != flag);
+    args.next()?;
+    args.next()
+}
@@ -0,0 +1,173 @@
+//! A stand-in League client for development without the real one.
+//! Serves the LCU REST routes we use over https and pushes
+//! `OnJsonApiEvent`s over a WAMP websocket on the same port.
+
+pub mod state;
+
+use crate::http;
+use crate::lcu::{self, basic_auth};
+use crate::logging;
+use crossbeam::channel::Sender;
+use kassadin::client::LCU;
+use native_tls::{Identity, TlsAcceptor};
+use serde_json::json;
+use std::io::{Read, Write};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use websocket::server::upgrade::Request as Head;
+use websocket::sync::server::upgrade::Buffer;
+use websocket::sync::Server;
+use websocket::OwnedMessage;
+
+pub use state::{MockScript, MockState};
+
+#[derive(Debug, Clone)]
+pub struct MockConfig {
+    /// 0 picks a free port
+    pub port: u16,
+    pub password: String,
+    pub script: MockScript,
+}
+
+impl Default for MockConfig {
+    fn default() -> Self {
+        Self {
+            port: 0,
+            password: "clowncher-mock".to_string(),
+            script: Default::default(),
+        }
+    }
+}
+
+/// Handle to a running mock client.
+#[derive(Clone)]
+pub struct MockClient {
+    pub port: u16,
+    pub password: String,
+    state: Arc<Mutex<MockState>>,
+    subscribers: Arc<Mutex<Vec<Sender<String>>>>,
+}
+
+impl MockClient {
+    /// An `LCU` talking to this mock instead of a real client.
+    pub fn lcu(&self) -> LCU {
+        lcu::from_credentials(self.port, &self.password)
+    }
+
+    /// Run `f` against the state, e.g. to script a phase from a test,
+    /// and publish whatever events it produced.
+    pub fn with_state<R>(&self, f: impl FnOnce(&mut MockState) -> R) -> R {
+        let result = {
+            let mut state = self.state.lock().unwrap();
+            f(&mut state)
+        };
+        self.flush();
+        result
+    }
+
+    fn flush(&self) {
+        let events: Vec<_> = self.state.lock().unwrap().events.drain(..).collect();
+        let mut subscribers = self.subscribers.lock().unwrap();
+        for (uri, event_type, data) in events {
+            let frame = json!([
+                8,
+                "OnJsonApiEvent",
+                { "data": data, "eventType": event_type, "uri": uri },
+            ])
+            .to_string();
+            subscribers.retain(|subscriber| subscriber.send(frame.clone()).is_ok());
+        }
+    }
+
+    /// Answer one REST request, the body is only read once the password checks out.
+    fn serve<S: Read + Write>(
+        &self,
+        stream: &mut S,
+        head: Head,
+        buffer: Option<Buffer>,
+    ) -> std::io::Result<()> {
+        let mut request = http::Request::parse(head);
+        let (status, body) = if request.authorization.as_deref()
+            != Some(basic_auth(&self.password).as_str())
+        {
+            (
+                401,
+                json!({ "errorCode": "UNAUTHORIZED", "httpStatus": 401 }),
+            )
+        } else if request.content_length > http::MAX_BODY {
+            (413, json!({ "errorCode": "RPC_ERROR", "httpStatus": 413 }))
+        } else {
+            request.read_body(stream, buffer)?;
+            self.with_state(|state| state.handle(&request.method, &request.path, request.json()))
+        };
+        log::debug!(
+            target: logging::MOCK,
+            "{} {} -> {}",
+            request.method,
+            request.path,
+            status
+        );
+        http::respond(stream, status, &body)
+    }
+}
+
+pub fn spawn(config: MockConfig) -> MockClient {
+    let cert =
+        rcgen::generate_simple_self_signed(vec!["127.0.0.1".to_string(), "localhost".to_string()])
+            .unwrap();
+    let identity = Identity::from_pkcs8(
+        cert.serialize_pem().unwrap().as_bytes(),
+        cert.serialize_private_key_pem().as_bytes(),
+    )
+    .unwrap();
+    let acceptor = TlsAcceptor::new(identity).unwrap();
+    let mut server = Server::bind_secure(("127.0.0.1", config.port), acceptor).unwrap();
+
+    let client = MockClient {
+        port: server.local_addr().unwrap().port(),
+        password: config.password,
+        state: Arc::new(Mutex::new(MockState::new(config.script))),
+        subscribers: Arc::new(Mutex::new(vec![])),
+    };
+
+    let ticker = client.clone();
+    std::thread::spawn(move || loop {
+        std::thread::sleep(Duration::from_secs(1));
+        ticker.with_state(|state| state.tick());
+    });
+
+    let mock = client.clone();
+    std::thread::spawn(move || loop {
+        match server.accept() {
+            Ok(upgrade) => {
+                let (send, recv) = crossbeam::channel::unbounded::<String>();
+                mock.subscribers.lock().unwrap().push(send);
+                std::thread::spawn(move || {
+                    let mut socket = match upgrade.accept() {
+                        Ok(socket) => socket,
+                        Err(_) => return,
+                    };
+                    // the client only ever subscribes to `OnJsonApiEvent`, so everything is pushed
+                    for frame in recv {
+                        if socket.send_message(&OwnedMessage::Text(frame)).is_err() {
+                            break;
+                        }
+                    }
+                });
+            }
+            Err(connection) => {
+                let (mut stream, head, buffer) =
+                    match (connection.stream, connection.parsed, connection.buffer) {
+                        (Some(stream), Some(head), buffer) => (stream, head, buffer),
+                        _ => continue,
+                    };
+                let mock = mock.clone();
+                std::thread::spawn(move || {
+                    let _ = mock.serve(&mut stream, head, buffer);
+                });
+            }
+        }
+    });
+
+    client
+}
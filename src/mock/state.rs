@@ -0,0 +1,1291 @@
+use crate::ui::chat::{self, conversation_uri};
+use crate::ui::friendlist::groups;
+use crate::ui::game::champ_select;
+use crate::ui::game::invitations::RECEIVED_INVITATIONS;
+use kassadin::routes;
+use serde_json::{json, Value};
+use std::time::Instant;
+
+/// conversation ids, with an `@` like the client's
+const LOBBY_ROOM: &str = "mock-party@sec.pvp.net";
+const CHAMP_SELECT_ROOM: &str = "mock-champ-select@champ-select.pvp.net";
+
+/// Timings of the scripted game loop the mock client runs through.
+#[derive(Debug, Clone)]
+pub struct MockScript {
+    /// seconds in queue until a match is found
+    pub queue_time: u64,
+    /// seconds until an unanswered ready check returns to the queue
+    pub ready_check_time: u64,
+    pub champ_select_time: u64,
+    pub game_time: u64,
+    /// every n ticks the first friend toggles between chat and away, 0 disables it
+    pub friend_flip_interval: u64,
+    /// every n ticks out of a lobby a friend invites us, 0 disables it
+    pub invite_interval: u64,
+    /// every n ticks a friend sends us a direct message, 0 disables it
+    pub message_interval: u64,
+}
+
+impl Default for MockScript {
+    fn default() -> Self {
+        Self {
+            queue_time: 10,
+            ready_check_time: 10,
+            champ_select_time: 30,
+            game_time: 30,
+            friend_flip_interval: 15,
+            invite_interval: 45,
+            message_interval: 25,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct MockMember {
+    pub summoner_id: i64,
+    pub puuid: String,
+    pub name: String,
+    pub first_position: String,
+    pub second_position: String,
+}
+
+impl MockMember {
+    fn to_json(&self, leader: bool) -> Value {
+        json!({
+            "puuid": self.puuid,
+            "summonerId": self.summoner_id,
+            "summonerName": self.name,
+            "summonerIconId": 29,
+            "isLeader": leader,
+            "autoFillEligible": false,
+            "firstPositionPreference": self.first_position,
+            "secondPositionPreference": self.second_position,
+        })
+    }
+}
+
+/// Everything the mock client knows, mutated by requests and the ticker.
+/// Every change pushes the websocket events the real client would send into `events`.
+#[derive(Debug)]
+pub struct MockState {
+    pub script: MockScript,
+    pub phase: &'static str,
+    pub phase_started: Instant,
+    pub queue_id: Option<i64>,
+    pub members: Vec<MockMember>,
+    pub friends: Vec<Value>,
+    pub friend_groups: Vec<Value>,
+    next_group: i64,
+    /// received invitations, as the client lists them
+    pub invitations: Vec<Value>,
+    /// invitations sent from the lobby, answered on the next tick
+    pub sent_invitations: Vec<Value>,
+    /// conversation id and message, of the rooms currently open and direct conversations
+    pub chat_messages: Vec<(String, Value)>,
+    /// chat ids of the friends we have a direct conversation with
+    pub direct_rooms: Vec<String>,
+    next_message: u64,
+    pub ticks: u64,
+    pub events: Vec<(String, &'static str, Value)>,
+}
+
+impl MockState {
+    pub fn new(script: MockScript) -> Self {
+        let friends = ["Teemo Main", "Yuumi Enjoyer", "Clown Fiesta", "Int Wizard"]
+            .iter()
+            .enumerate()
+            .map(|(idx, name)| {
+                let summoner_id = idx as i64 + 2;
+                json!({
+                    "id": format!("mock-puuid-{}@eu1.pvp.net", summoner_id),
+                    "puuid": format!("mock-puuid-{}", summoner_id),
+                    "summonerId": summoner_id,
+                    "gameName": name,
+                    "gameTag": "MOCK",
+                    "name": name,
+                    "icon": 29,
+                    "availability": if idx % 2 == 0 { "chat" } else { "offline" },
+                    "groupId": if idx == 3 { 1 } else { 0 },
+                    "groupName": if idx == 3 { "Duo" } else { "**Default" },
+                    "displayGroupName": "",
+                    "note": "",
+                    "lol": {
+                        "rankedLeagueDivision": "II",
+                        "rankedLeagueTier": "GOLD",
+                    },
+                })
+            })
+            .collect();
+
+        Self {
+            script,
+            phase: "None",
+            phase_started: Instant::now(),
+            queue_id: None,
+            members: vec![Self::local_member()],
+            friends,
+            friend_groups: vec![friend_group(0, "**Default", 0), friend_group(1, "Duo", 1)],
+            next_group: 2,
+            invitations: vec![],
+            sent_invitations: vec![],
+            chat_messages: vec![],
+            direct_rooms: vec![],
+            next_message: 0,
+            ticks: 0,
+            events: vec![],
+        }
+    }
+
+    fn local_member() -> MockMember {
+        MockMember {
+            summoner_id: 1,
+            puuid: "mock-puuid-1".to_string(),
+            name: "Clowncher".to_string(),
+            first_position: "UNSELECTED".to_string(),
+            second_position: "UNSELECTED".to_string(),
+        }
+    }
+
+    fn publish(&mut self, uri: &str, event_type: &'static str, data: Value) {
+        self.events.push((uri.to_string(), event_type, data));
+    }
+
+    fn set_phase(&mut self, phase: &'static str) {
+        if self.phase == "ChampSelect" && phase != "ChampSelect" {
+            self.publish(champ_select::SESSION, "Delete", Value::Null);
+            self.close_room(CHAMP_SELECT_ROOM);
+        }
+        if self.phase != "ChampSelect" && phase == "ChampSelect" {
+            self.open_room(CHAMP_SELECT_ROOM, chat::CHAMP_SELECT);
+        }
+        self.phase = phase;
+        self.phase_started = Instant::now();
+        let session = self.gameflow_session();
+        self.publish(routes::game_flow::SESSION, "Update", session);
+        if phase != "Matchmaking" && phase != "ReadyCheck" {
+            self.publish(routes::matchmaking::SEARCH, "Delete", Value::Null);
+        }
+    }
+
+    fn elapsed(&self) -> u64 {
+        self.phase_started.elapsed().as_secs()
+    }
+
+    pub fn gameflow_session(&self) -> Value {
+        json!({
+            "phase": self.phase,
+            "gameData": {
+                "queue": { "id": self.queue_id.unwrap_or(-1) },
+            },
+        })
+    }
+
+    pub fn lobby(&self) -> Option<Value> {
+        let queue_id = self.queue_id?;
+        let members: Vec<Value> = self
+            .members
+            .iter()
+            .enumerate()
+            .map(|(idx, member)| member.to_json(idx == 0))
+            .collect();
+        Some(json!({
+            "partyId": "mock-party",
+            "canStartActivity": true,
+            "gameConfig": { "queueId": queue_id, "isCustom": false },
+            "localMember": members[0].clone(),
+            "members": members,
+            "invitations": self.sent_invitations,
+        }))
+    }
+
+    fn publish_lobby(&mut self, event_type: &'static str) {
+        let lobby = self.lobby().unwrap_or(Value::Null);
+        self.publish(routes::lobby::LOBBY, event_type, lobby);
+    }
+
+    pub fn search(&self) -> Value {
+        let in_ready_check = self.phase == "ReadyCheck";
+        let (search_state, time_in_queue, ready_check_state, ready_check_timer) = if in_ready_check
+        {
+            (
+                "Found",
+                self.script.queue_time,
+                "InProgress",
+                self.elapsed(),
+            )
+        } else {
+            ("Searching", self.elapsed(), "Invalid", 0)
+        };
+        json!({
+            "searchState": search_state,
+            "timeInQueue": time_in_queue as f64,
+            "estimatedQueueTime": self.script.queue_time as f64,
+            "readyCheck": {
+                "state": ready_check_state,
+                "playerResponse": "None",
+                "timer": ready_check_timer as f64,
+            },
+        })
+    }
+
+    pub fn champ_select_session(&self) -> Value {
+        let my_team: Vec<Value> = self
+            .members
+            .iter()
+            .enumerate()
+            .map(|(cell_id, member)| {
+                json!({
+                    "cellId": cell_id,
+                    "summonerId": member.summoner_id,
+                    "assignedPosition": member.first_position.to_lowercase(),
+                    "championId": 0,
+                    "championPickIntent": 0,
+                    "spell1Id": 4,
+                    "spell2Id": 14,
+                    "team": 1,
+                })
+            })
+            .collect();
+        // bans for the first third, picks afterwards
+        let bans_done = self.elapsed() * 3 >= self.script.champ_select_time;
+        let action = |cell_id: usize, kind: &str, completed: bool, in_progress: bool| {
+            json!({
+                "id": cell_id + if kind == "ban" { 0 } else { 10 },
+                "actorCellId": cell_id,
+                "championId": if completed { 1 + cell_id } else { 0 },
+                "type": kind,
+                "completed": completed,
+                "isAllyAction": true,
+                "isInProgress": in_progress,
+            })
+        };
+        let bans: Vec<Value> = (0..my_team.len())
+            .map(|cell_id| action(cell_id, "ban", bans_done, !bans_done))
+            .collect();
+        let picks: Vec<Value> = (0..my_team.len())
+            .map(|cell_id| action(cell_id, "pick", false, bans_done))
+            .collect();
+
+        let left = self.script.champ_select_time.saturating_sub(self.elapsed());
+        json!({
+            "localPlayerCellId": 0,
+            "myTeam": my_team,
+            "theirTeam": [],
+            "actions": [bans, picks],
+            "bans": { "myTeamBans": [], "theirTeamBans": [], "numBans": 0 },
+            "timer": {
+                "phase": "BAN_PICK",
+                "adjustedTimeLeftInPhase": left * 1000,
+                "totalTimeInPhase": self.script.champ_select_time * 1000,
+                "isInfinite": false,
+            },
+        })
+    }
+
+    pub fn ranked_stats(&self, puuid: &str) -> Value {
+        const TIERS: [&str; 6] = ["IRON", "BRONZE", "SILVER", "GOLD", "PLATINUM", "DIAMOND"];
+        const DIVISIONS: [&str; 4] = ["I", "II", "III", "IV"];
+        let seed = puuid.bytes().map(|b| b as usize).sum::<usize>();
+        let queue = |queue_type: &str, offset: usize| {
+            json!({
+                "queueType": queue_type,
+                "tier": TIERS[(seed + offset) % TIERS.len()],
+                "division": DIVISIONS[(seed + offset) % DIVISIONS.len()],
+                "leaguePoints": (seed * 7 + offset) % 100,
+                "wins": (seed + offset) % 150,
+                "losses": (seed * 3 + offset) % 150,
+                "isProvisional": false,
+                "provisionalGamesRemaining": 0,
+                "previousSeasonEndTier": TIERS[(seed + offset + 1) % TIERS.len()],
+                "previousSeasonEndDivision": "IV",
+                "miniSeriesProgress": "",
+            })
+        };
+        json!({
+            "queueMap": {
+                "RANKED_SOLO_5x5": queue("RANKED_SOLO_5x5", 0),
+                "RANKED_FLEX_SR": queue("RANKED_FLEX_SR", 1),
+            },
+        })
+    }
+
+    /// Advance the script by one second.
+    pub fn tick(&mut self) {
+        self.ticks += 1;
+        match self.phase {
+            "Matchmaking" => {
+                if self.elapsed() >= self.script.queue_time {
+                    self.set_phase("ReadyCheck");
+                }
+                let search = self.search();
+                self.publish(routes::matchmaking::SEARCH, "Update", search);
+            }
+            "ReadyCheck" if self.elapsed() >= self.script.ready_check_time => {
+                self.set_phase("Matchmaking");
+            }
+            "ChampSelect" => {
+                if self.elapsed() >= self.script.champ_select_time {
+                    self.set_phase("InProgress");
+                } else {
+                    let session = self.champ_select_session();
+                    self.publish(champ_select::SESSION, "Update", session);
+                }
+            }
+            "InProgress" if self.elapsed() >= self.script.game_time => {
+                self.set_phase("WaitingForStats");
+            }
+            "WaitingForStats" => {
+                self.set_phase("EndOfGame");
+            }
+            "EndOfGame" => {
+                self.set_phase("Lobby");
+                self.publish_lobby("Update");
+            }
+            _ => {}
+        }
+
+        if self.answer_sent_invitations() {
+            self.publish_lobby("Update");
+        }
+
+        let interval = self.script.message_interval;
+        if interval != 0 && self.ticks.is_multiple_of(interval) {
+            let id = self.friends.get(2).and_then(|friend| friend["id"].as_str());
+            if let Some(id) = id.map(str::to_string) {
+                self.receive_message(&id, "you up?");
+            }
+        }
+
+        let interval = self.script.invite_interval;
+        let pending = self
+            .invitations
+            .iter()
+            .any(|invitation| invitation["state"] == "Pending");
+        if interval != 0 && self.ticks.is_multiple_of(interval) && self.phase == "None" && !pending
+        {
+            self.receive_invitation(0);
+        }
+
+        let interval = self.script.friend_flip_interval;
+        if interval != 0 && self.ticks.is_multiple_of(interval) && !self.friends.is_empty() {
+            let friend = &mut self.friends[0];
+            friend["availability"] = if friend["availability"] == "chat" {
+                json!("away")
+            } else {
+                json!("chat")
+            };
+            let friend = friend.clone();
+            let uri = format!(
+                "{}/{}",
+                routes::chat::FRIENDS,
+                friend["id"].as_str().unwrap()
+            );
+            self.publish(&uri, "Update", friend);
+        }
+    }
+
+    fn room_kind(id: &str) -> &'static str {
+        if id == LOBBY_ROOM {
+            chat::LOBBY
+        } else {
+            chat::CHAMP_SELECT
+        }
+    }
+
+    /// The conversations of the rooms we are in.
+    pub fn conversations(&self) -> Value {
+        let mut rooms = vec![];
+        if self.queue_id.is_some() {
+            rooms.push(LOBBY_ROOM);
+        }
+        if self.phase == "ChampSelect" {
+            rooms.push(CHAMP_SELECT_ROOM);
+        }
+        let mut conversations = rooms
+            .into_iter()
+            .map(|id| conversation(id, Self::room_kind(id), ""))
+            .collect::<Vec<_>>();
+        for id in &self.direct_rooms {
+            conversations.push(self.direct_conversation(id));
+        }
+        Value::Array(conversations)
+    }
+
+    fn open_room(&mut self, id: &str, kind: &str) {
+        self.publish(&conversation_uri(id), "Create", conversation(id, kind, ""));
+        self.post_message(id, 1, "system", "joined_room");
+    }
+
+    fn publish_friend_groups(&mut self) {
+        let groups = Value::Array(self.friend_groups.clone());
+        self.publish(groups::FRIEND_GROUPS, "Update", groups);
+    }
+
+    /// Index of the group `id` unless it's the default one, which can't be changed.
+    fn editable_group(&self, id: &str) -> Option<usize> {
+        let id = id.parse::<i64>().ok()?;
+        self.friend_groups
+            .iter()
+            .position(|group| group["id"] == id && group["name"] != "**Default")
+    }
+
+    /// Put the friend `id` into `group_id`, `false` if either doesn't exist.
+    fn move_friend(&mut self, id: &str, group_id: i64) -> bool {
+        let name = match self
+            .friend_groups
+            .iter()
+            .find(|group| group["id"] == group_id)
+        {
+            Some(group) => group["name"].clone(),
+            None => return false,
+        };
+        let friend = match self.friends.iter_mut().find(|friend| friend["id"] == id) {
+            Some(friend) => friend,
+            None => return false,
+        };
+        friend["groupId"] = json!(group_id);
+        friend["groupName"] = name;
+        let friend = friend.clone();
+        let uri = format!("{}/{}", routes::chat::FRIENDS, chat::escape(id));
+        self.publish(&uri, "Update", friend);
+        true
+    }
+
+    fn friend(&self, id: &str) -> Option<&Value> {
+        self.friends.iter().find(|friend| friend["id"] == id)
+    }
+
+    fn direct_conversation(&self, id: &str) -> Value {
+        let name = self
+            .friend(id)
+            .and_then(|friend| friend["gameName"].as_str())
+            .unwrap_or_default();
+        conversation(id, chat::DIRECT, name)
+    }
+
+    /// Start the direct conversation with the friend `id` unless there is one.
+    fn open_direct(&mut self, id: &str) -> Value {
+        let conversation = self.direct_conversation(id);
+        if !self.direct_rooms.iter().any(|room| room == id) {
+            self.direct_rooms.push(id.to_string());
+            self.publish(&conversation_uri(id), "Create", conversation.clone());
+        }
+        conversation
+    }
+
+    /// The friend `id` writes to us.
+    pub fn receive_message(&mut self, id: &str, body: &str) {
+        let summoner_id = match self.friend(id) {
+            Some(friend) => friend["summonerId"].as_i64().unwrap_or_default(),
+            None => return,
+        };
+        self.open_direct(id);
+        self.post_message(id, summoner_id, "chat", body);
+    }
+
+    fn close_room(&mut self, id: &str) {
+        self.chat_messages.retain(|(room, _)| room != id);
+        self.publish(&conversation_uri(id), "Delete", Value::Null);
+    }
+
+    fn post_message(&mut self, room: &str, from: i64, kind: &str, body: &str) -> Value {
+        let message = json!({
+            "id": format!("mock-message-{}", self.next_message),
+            "body": body,
+            "fromId": format!("mock-puuid-{}@eu1.pvp.net", from),
+            "fromSummonerId": from,
+            "timestamp": chrono::Utc::now().to_rfc3339(),
+            "type": kind,
+        });
+        self.next_message += 1;
+        self.chat_messages.push((room.to_string(), message.clone()));
+        let uri = format!(
+            "{}/messages/{}",
+            conversation_uri(room),
+            message["id"].as_str().unwrap()
+        );
+        self.publish(&uri, "Create", message.clone());
+        message
+    }
+
+    fn publish_invitations(&mut self) {
+        let invitations = Value::Array(self.invitations.clone());
+        self.publish(RECEIVED_INVITATIONS, "Update", invitations);
+    }
+
+    /// The friend at `idx` invites us into a draft lobby.
+    pub fn receive_invitation(&mut self, idx: usize) {
+        let friend = match self.friends.get(idx) {
+            Some(friend) => friend.clone(),
+            None => return,
+        };
+        let invitation = json!({
+            "invitationId": format!("mock-invitation-{}", self.ticks),
+            "fromSummonerId": friend["summonerId"],
+            "fromSummonerName": friend["gameName"],
+            "state": "Pending",
+            "timestamp": chrono::Local::now().timestamp_millis().to_string(),
+            "gameConfig": { "queueId": 400, "isCustom": false },
+            "canAcceptInvitation": true,
+        });
+        self.invitations.push(invitation);
+        self.publish_invitations();
+    }
+
+    /// Answer the invitation `id` with `state`, `None` if there is no such invitation.
+    fn answer_invitation(&mut self, id: &str, state: &str) -> Option<Value> {
+        let invitation = self
+            .invitations
+            .iter_mut()
+            .find(|invitation| invitation["invitationId"] == id)?;
+        invitation["state"] = json!(state);
+        let invitation = invitation.clone();
+        self.publish_invitations();
+        Some(invitation)
+    }
+
+    /// Friends online accept, everyone else declines. `true` if any was answered.
+    fn answer_sent_invitations(&mut self) -> bool {
+        let mut answered = false;
+        for idx in 0..self.sent_invitations.len() {
+            if self.sent_invitations[idx]["state"] != "Pending" {
+                continue;
+            }
+            let summoner_id = self.sent_invitations[idx]["toSummonerId"].as_i64();
+            let friend = self
+                .friends
+                .iter()
+                .find(|f| f["summonerId"].as_i64() == summoner_id)
+                .cloned()
+                .unwrap_or_default();
+            let accepted = friend["availability"] == "chat";
+            if accepted
+                && self
+                    .members
+                    .iter()
+                    .all(|m| Some(m.summoner_id) != summoner_id)
+            {
+                self.members.push(MockMember {
+                    summoner_id: summoner_id.unwrap_or_default(),
+                    puuid: friend["puuid"].as_str().unwrap_or_default().to_string(),
+                    name: friend["gameName"].as_str().unwrap_or_default().to_string(),
+                    first_position: "FILL".to_string(),
+                    second_position: "UNSELECTED".to_string(),
+                });
+                self.post_message(
+                    LOBBY_ROOM,
+                    summoner_id.unwrap_or_default(),
+                    "system",
+                    "joined_room",
+                );
+            }
+            self.sent_invitations[idx]["state"] =
+                json!(if accepted { "Accepted" } else { "Declined" });
+            answered = true;
+        }
+        answered
+    }
+
+    /// Answer a REST request the way the client would, returning status and body.
+    pub fn handle(&mut self, method: &str, path: &str, body: Value) -> (u16, Value) {
+        let segments: Vec<&str> = path.trim_matches('/').split('/').collect();
+        match (method, segments.as_slice()) {
+            ("GET", ["lol-summoner", "v1", "current-summoner"]) => {
+                let member = &self.members[0];
+                (
+                    200,
+                    json!({
+                        "summonerId": member.summoner_id,
+                        "puuid": member.puuid,
+                        "displayName": member.name,
+                        "summonerLevel": 30,
+                        "profileIconId": 29,
+                    }),
+                )
+            }
+            ("GET", ["lol-gameflow", "v1", "session"]) => (200, self.gameflow_session()),
+            ("GET", ["lol-gameflow", "v1", "gameflow-phase"]) => (200, json!(self.phase)),
+            ("GET", ["lol-lobby", "v2", "lobby"]) => match self.lobby() {
+                Some(lobby) => (200, lobby),
+                None => not_found(path),
+            },
+            ("POST", ["lol-lobby", "v2", "lobby"]) => {
+                let created = self.queue_id.is_none();
+                self.queue_id = body["queueId"].as_i64();
+                self.members.truncate(1);
+                self.sent_invitations.clear();
+                self.publish_lobby(if created { "Create" } else { "Update" });
+                if created {
+                    self.open_room(LOBBY_ROOM, chat::LOBBY);
+                }
+                self.set_phase("Lobby");
+                (200, self.lobby().unwrap_or(Value::Null))
+            }
+            ("DELETE", ["lol-lobby", "v2", "lobby"]) => {
+                self.queue_id = None;
+                self.members.truncate(1);
+                self.sent_invitations.clear();
+                self.publish_lobby("Delete");
+                self.close_room(LOBBY_ROOM);
+                self.set_phase("None");
+                (204, Value::Null)
+            }
+            (
+                "PUT",
+                ["lol-lobby", "v2", "lobby", "members", "localMember", "position-preferences"],
+            ) => {
+                let local = &mut self.members[0];
+                local.first_position = position(&body["firstPreference"]);
+                local.second_position = position(&body["secondPreference"]);
+                self.publish_lobby("Update");
+                (204, Value::Null)
+            }
+            ("POST", ["lol-lobby", "v2", "lobby", "matchmaking", "search"]) => {
+                if self.queue_id.is_none() {
+                    return conflict("not in a lobby");
+                }
+                self.set_phase("Matchmaking");
+                let search = self.search();
+                self.publish(routes::matchmaking::SEARCH, "Create", search);
+                (204, Value::Null)
+            }
+            ("DELETE", ["lol-lobby", "v2", "lobby", "matchmaking", "search"]) => {
+                self.set_phase("Lobby");
+                (204, Value::Null)
+            }
+            ("POST", ["lol-lobby", "v2", "lobby", "invitations"]) => {
+                let invited: Vec<i64> = body
+                    .as_array()
+                    .map(|invites| {
+                        invites
+                            .iter()
+                            .filter_map(|invite| invite["toSummonerId"].as_i64())
+                            .collect()
+                    })
+                    .unwrap_or_default();
+                for summoner_id in invited {
+                    let friend = self
+                        .friends
+                        .iter()
+                        .find(|f| f["summonerId"].as_i64() == Some(summoner_id))
+                        .cloned();
+                    if let Some(friend) = friend {
+                        // a new invitation replaces an answered one, like in the client
+                        self.sent_invitations
+                            .retain(|invite| invite["toSummonerId"].as_i64() != Some(summoner_id));
+                        self.sent_invitations.push(json!({
+                            "invitationId": format!("mock-sent-{}-{}", summoner_id, self.ticks),
+                            "toSummonerId": summoner_id,
+                            "toSummonerName": friend["gameName"],
+                            "state": "Pending",
+                        }));
+                    }
+                }
+                self.publish_lobby("Update");
+                (200, body)
+            }
+            ("POST", ["lol-lobby", "v2", "lobby", "members", id, "kick"]) => {
+                let id = id.parse::<i64>().unwrap_or_default();
+                self.members
+                    .retain(|m| m.summoner_id != id || m.summoner_id == 1);
+                // kicking someone invited revokes the invitation
+                for invite in &mut self.sent_invitations {
+                    if invite["toSummonerId"].as_i64() == Some(id) && invite["state"] == "Pending" {
+                        invite["state"] = json!("Kicked");
+                    }
+                }
+                self.publish_lobby("Update");
+                (204, Value::Null)
+            }
+            ("GET", ["lol-matchmaking", "v1", "search"]) => match self.phase {
+                "Matchmaking" | "ReadyCheck" => (200, self.search()),
+                _ => not_found(path),
+            },
+            ("POST", ["lol-matchmaking", "v1", "ready-check", "accept"]) => {
+                if self.phase != "ReadyCheck" {
+                    return conflict("no ready check in progress");
+                }
+                self.set_phase("ChampSelect");
+                (204, Value::Null)
+            }
+            ("POST", ["lol-matchmaking", "v1", "ready-check", "decline"]) => {
+                if self.phase != "ReadyCheck" {
+                    return conflict("no ready check in progress");
+                }
+                self.set_phase("Lobby");
+                (204, Value::Null)
+            }
+            ("GET", ["lol-champ-select", "v1", "session"]) => match self.phase {
+                "ChampSelect" => (200, self.champ_select_session()),
+                _ => not_found(path),
+            },
+            ("POST", ["lol-login", "v1", "session", "invoke"]) => {
+                // the only invoke we use is the champ select dodge
+                if self.phase == "ChampSelect" {
+                    self.set_phase("Lobby");
+                }
+                (200, Value::Null)
+            }
+            ("GET", ["lol-ranked", "v1", "ranked-stats", puuid]) => (200, self.ranked_stats(puuid)),
+            ("GET", ["lol-chat", "v1", "friends"]) => (200, Value::Array(self.friends.clone())),
+            ("PUT", ["lol-chat", "v1", "friends", id]) => {
+                let id = chat::unescape(id);
+                match body["groupId"].as_i64() {
+                    Some(group_id) if self.move_friend(&id, group_id) => (204, Value::Null),
+                    _ => not_found(path),
+                }
+            }
+            ("GET", ["lol-chat", "v1", "friend-groups"]) => {
+                (200, Value::Array(self.friend_groups.clone()))
+            }
+            ("POST", ["lol-chat", "v1", "friend-groups"]) => {
+                let name = body["name"].as_str().unwrap_or_default();
+                if name.is_empty() || self.friend_groups.iter().any(|group| group["name"] == name) {
+                    return conflict("a group with that name exists");
+                }
+                let group = friend_group(self.next_group, name, self.next_group);
+                self.next_group += 1;
+                self.friend_groups.push(group.clone());
+                self.publish_friend_groups();
+                (200, group)
+            }
+            ("PUT", ["lol-chat", "v1", "friend-groups", id]) => {
+                let name = body["name"].as_str().unwrap_or_default().to_string();
+                if name.is_empty() || self.friend_groups.iter().any(|group| group["name"] == name) {
+                    return conflict("a group with that name exists");
+                }
+                match self.editable_group(id) {
+                    Some(idx) => self.friend_groups[idx]["name"] = json!(name),
+                    None => return not_found(path),
+                }
+                self.publish_friend_groups();
+                (204, Value::Null)
+            }
+            ("DELETE", ["lol-chat", "v1", "friend-groups", id]) => {
+                let group_id = match self.editable_group(id) {
+                    Some(idx) => self.friend_groups[idx]["id"].as_i64().unwrap_or_default(),
+                    None => return not_found(path),
+                };
+                // the client moves its friends back to the default group
+                let moved = self
+                    .friends
+                    .iter()
+                    .filter(|friend| friend["groupId"] == group_id)
+                    .filter_map(|friend| friend["id"].as_str().map(str::to_string))
+                    .collect::<Vec<_>>();
+                for friend in moved {
+                    self.move_friend(&friend, 0);
+                }
+                self.friend_groups.retain(|group| group["id"] != group_id);
+                self.publish_friend_groups();
+                (204, Value::Null)
+            }
+            ("GET", ["lol-game-queues", "v1", "queues"]) => (200, queues()),
+            ("GET", ["lol-chat", "v1", "conversations"]) => (200, self.conversations()),
+            ("GET", ["lol-chat", "v1", "conversations", id, "messages"]) => {
+                let id = chat::unescape(id);
+                let messages = self
+                    .chat_messages
+                    .iter()
+                    .filter(|(room, _)| *room == id)
+                    .map(|(_, message)| message.clone())
+                    .collect();
+                (200, Value::Array(messages))
+            }
+            ("POST", ["lol-chat", "v1", "conversations"]) => {
+                let id = body["id"].as_str().unwrap_or_default().to_string();
+                if self.friend(&id).is_none() {
+                    return not_found(path);
+                }
+                (200, self.open_direct(&id))
+            }
+            ("POST", ["lol-chat", "v1", "conversations", id, "messages"]) => {
+                let id = chat::unescape(id);
+                let direct = self.direct_rooms.contains(&id);
+                if !direct && !self.chat_messages.iter().any(|(room, _)| *room == id) {
+                    return not_found(path);
+                }
+                let kind = body["type"].as_str().unwrap_or("chat").to_string();
+                let text = body["body"].as_str().unwrap_or_default().to_string();
+                let message = self.post_message(&id, 1, &kind, &text);
+                if direct {
+                    self.receive_message(&id, "lol");
+                }
+                (200, message)
+            }
+            ("GET", ["lol-chat", "v1", "conversations", _, "participants"]) => {
+                let participants = self
+                    .members
+                    .iter()
+                    .map(|member| {
+                        json!({
+                            "name": member.name,
+                            "gameName": member.name,
+                            "summonerId": member.summoner_id,
+                        })
+                    })
+                    .collect();
+                (200, Value::Array(participants))
+            }
+            ("GET", ["lol-lobby", "v2", "received-invitations"]) => {
+                (200, Value::Array(self.invitations.clone()))
+            }
+            ("POST", ["lol-lobby", "v2", "received-invitations", id, "accept"]) => {
+                let invitation = match self.answer_invitation(id, "Accepted") {
+                    Some(invitation) => invitation,
+                    None => return not_found(path),
+                };
+                let created = self.queue_id.is_none();
+                self.queue_id = invitation["gameConfig"]["queueId"].as_i64();
+                self.members.truncate(1);
+                self.sent_invitations.clear();
+                self.members.push(MockMember {
+                    summoner_id: invitation["fromSummonerId"].as_i64().unwrap_or_default(),
+                    puuid: format!("mock-puuid-{}", invitation["fromSummonerId"]),
+                    name: invitation["fromSummonerName"]
+                        .as_str()
+                        .unwrap_or_default()
+                        .to_string(),
+                    first_position: "FILL".to_string(),
+                    second_position: "UNSELECTED".to_string(),
+                });
+                self.publish_lobby(if created { "Create" } else { "Update" });
+                if created {
+                    self.open_room(LOBBY_ROOM, chat::LOBBY);
+                }
+                self.set_phase("Lobby");
+                (204, Value::Null)
+            }
+            ("POST", ["lol-lobby", "v2", "received-invitations", id, "decline"]) => {
+                match self.answer_invitation(id, "Declined") {
+                    Some(_) => (204, Value::Null),
+                    None => not_found(path),
+                }
+            }
+            ("POST", ["lol-lobby", "v2", "eligibility", "game-select-eligibility-self"]) => {
+                (200, eligibility())
+            }
+            _ => not_found(path),
+        }
+    }
+}
+
+/// A few queues of every group, one of them disabled and one we are too low level for.
+fn queues() -> Value {
+    let queue = |id: i64, name: &str, category: &str, mode: &str, map: i64, available: &str| {
+        json!({
+            "id": id,
+            "name": name,
+            "description": name,
+            "category": category,
+            "gameMode": mode,
+            "mapId": map,
+            "queueAvailability": available,
+            "isRanked": id == 420 || id == 440 || id == 1100,
+            "minLevel": match id {
+                420 => 30,
+                // above the mock summoner, for a restriction to show
+                440 => 50,
+                _ => 0,
+            },
+        })
+    };
+    json!([
+        queue(400, "Draft Pick", "PvP", "CLASSIC", 11, "Available"),
+        queue(420, "Ranked Solo/Duo", "PvP", "CLASSIC", 11, "Available"),
+        queue(430, "Blind Pick", "PvP", "CLASSIC", 11, "Available"),
+        queue(440, "Ranked Flex", "PvP", "CLASSIC", 11, "Available"),
+        queue(450, "ARAM", "PvP", "ARAM", 12, "Available"),
+        queue(700, "Clash", "PvP", "CLASSIC", 11, "PlatformDisabled"),
+        queue(900, "ARURF", "PvP", "URF", 11, "Available"),
+        queue(830, "Intro", "VersusAi", "CLASSIC", 11, "Available"),
+        queue(850, "Intermediate", "VersusAi", "CLASSIC", 11, "Available"),
+        queue(1090, "Teamfight Tactics", "PvP", "TFT", 22, "Available"),
+        queue(
+            1100,
+            "Ranked Teamfight Tactics",
+            "PvP",
+            "TFT",
+            22,
+            "Available"
+        ),
+        queue(0, "Custom", "Custom", "CLASSIC", 11, "Available"),
+    ])
+}
+
+/// Queues missing here count as eligible.
+fn eligibility() -> Value {
+    json!([
+        { "queueId": 400, "eligible": true, "restrictions": [] },
+        { "queueId": 420, "eligible": true, "restrictions": [] },
+        {
+            "queueId": 440,
+            "eligible": false,
+            "restrictions": [{ "restrictionCode": "PlayerMinLevelRestriction" }],
+        },
+    ])
+}
+
+fn position(value: &Value) -> String {
+    value.as_str().unwrap_or("UNSELECTED").to_string()
+}
+
+fn not_found(path: &str) -> (u16, Value) {
+    (
+        404,
+        json!({
+            "errorCode": "RPC_ERROR",
+            "httpStatus": 404,
+            "message": format!("Invalid URI format: {}", path),
+        }),
+    )
+}
+
+fn conflict(message: &str) -> (u16, Value) {
+    (
+        409,
+        json!({
+            "errorCode": "RPC_ERROR",
+            "httpStatus": 409,
+            "message": message,
+        }),
+    )
+}
+
+fn friend_group(id: i64, name: &str, priority: i64) -> Value {
+    json!({
+        "id": id,
+        "name": name,
+        "priority": priority,
+        "isMetaGroup": false,
+        "isLocalized": name == "**Default",
+        "collapsed": false,
+    })
+}
+
+fn conversation(id: &str, kind: &str, name: &str) -> Value {
+    json!({
+        "id": id,
+        "type": kind,
+        "name": name,
+        "unreadMessageCount": 0,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    /// no timers firing on their own, only what the test does
+    fn quiet() -> MockState {
+        MockState::new(MockScript {
+            friend_flip_interval: 0,
+            invite_interval: 0,
+            message_interval: 0,
+            ..Default::default()
+        })
+    }
+
+    /// Pretend the current phase started `secs` ago.
+    fn rewind(state: &mut MockState, secs: u64) {
+        state.phase_started = Instant::now() - Duration::from_secs(secs);
+    }
+
+    fn published(state: &mut MockState, uri: &str) -> Vec<(&'static str, Value)> {
+        state
+            .events
+            .drain(..)
+            .filter(|(event_uri, _, _)| event_uri == uri)
+            .map(|(_, event_type, data)| (event_type, data))
+            .collect()
+    }
+
+    #[test]
+    fn lobby_is_created_and_left() {
+        let mut state = quiet();
+        assert_eq!(
+            state.handle("GET", "/lol-lobby/v2/lobby", Value::Null).0,
+            404
+        );
+
+        let (status, lobby) =
+            state.handle("POST", "/lol-lobby/v2/lobby", json!({ "queueId": 420 }));
+        assert_eq!(status, 200);
+        assert_eq!(lobby["gameConfig"]["queueId"], 420);
+        assert_eq!(lobby["localMember"]["isLeader"], true);
+        assert_eq!(state.phase, "Lobby");
+        let events = published(&mut state, routes::lobby::LOBBY);
+        assert_eq!(events[0].0, "Create");
+
+        let (status, _) = state.handle("DELETE", "/lol-lobby/v2/lobby", Value::Null);
+        assert_eq!(status, 204);
+        assert_eq!(state.phase, "None");
+        assert_eq!(
+            state.handle("GET", "/lol-lobby/v2/lobby", Value::Null).0,
+            404
+        );
+    }
+
+    #[test]
+    fn invited_friends_answer_on_the_next_tick() {
+        let mut state = quiet();
+        state.handle("POST", "/lol-lobby/v2/lobby", json!({ "queueId": 400 }));
+        // summoner 2 is online, 3 is offline, 99 isn't a friend
+        let invites = json!([
+            { "toSummonerId": 2 },
+            { "toSummonerId": 3 },
+            { "toSummonerId": 99 },
+        ]);
+        assert_eq!(
+            state
+                .handle("POST", "/lol-lobby/v2/lobby/invitations", invites)
+                .0,
+            200
+        );
+        let lobby = state.lobby().unwrap();
+        assert_eq!(lobby["invitations"].as_array().unwrap().len(), 2);
+        assert!(lobby["invitations"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .all(|invite| invite["state"] == "Pending"));
+
+        state.tick();
+        let lobby = state.lobby().unwrap();
+        assert_eq!(lobby["invitations"][0]["state"], "Accepted");
+        assert_eq!(lobby["invitations"][1]["state"], "Declined");
+        assert_eq!(lobby["members"].as_array().unwrap().len(), 2);
+        assert_eq!(lobby["members"][1]["summonerId"], 2);
+    }
+
+    #[test]
+    fn kicking_removes_members_but_not_ourselves() {
+        let mut state = quiet();
+        state.handle("POST", "/lol-lobby/v2/lobby", json!({ "queueId": 400 }));
+        state.handle(
+            "POST",
+            "/lol-lobby/v2/lobby/invitations",
+            json!([{ "toSummonerId": 2 }, { "toSummonerId": 4 }]),
+        );
+        state.tick();
+        assert_eq!(state.members.len(), 3);
+
+        let (status, _) = state.handle("POST", "/lol-lobby/v2/lobby/members/2/kick", Value::Null);
+        assert_eq!(status, 204);
+        state.handle("POST", "/lol-lobby/v2/lobby/members/1/kick", Value::Null);
+        let ids: Vec<i64> = state.members.iter().map(|m| m.summoner_id).collect();
+        assert_eq!(ids, [1, 4]);
+    }
+
+    #[test]
+    fn search_needs_a_lobby() {
+        let mut state = quiet();
+        let path = "/lol-lobby/v2/lobby/matchmaking/search";
+        assert_eq!(state.handle("POST", path, Value::Null).0, 409);
+
+        state.handle("POST", "/lol-lobby/v2/lobby", json!({ "queueId": 400 }));
+        assert_eq!(state.handle("POST", path, Value::Null).0, 204);
+        assert_eq!(state.phase, "Matchmaking");
+        let (status, search) = state.handle("GET", "/lol-matchmaking/v1/search", Value::Null);
+        assert_eq!(status, 200);
+        assert_eq!(search["searchState"], "Searching");
+
+        assert_eq!(state.handle("DELETE", path, Value::Null).0, 204);
+        assert_eq!(state.phase, "Lobby");
+        assert_eq!(
+            state
+                .handle("GET", "/lol-matchmaking/v1/search", Value::Null)
+                .0,
+            404
+        );
+    }
+
+    /// In queue for long enough to get a ready check.
+    fn ready_check() -> MockState {
+        let mut state = quiet();
+        state.handle("POST", "/lol-lobby/v2/lobby", json!({ "queueId": 400 }));
+        state.handle(
+            "POST",
+            "/lol-lobby/v2/lobby/matchmaking/search",
+            Value::Null,
+        );
+        let queue_time = state.script.queue_time;
+        rewind(&mut state, queue_time);
+        state.tick();
+        assert_eq!(state.phase, "ReadyCheck");
+        state
+    }
+
+    #[test]
+    fn ready_check_is_accepted_into_champ_select() {
+        let mut state = ready_check();
+        let (_, search) = state.handle("GET", "/lol-matchmaking/v1/search", Value::Null);
+        assert_eq!(search["readyCheck"]["state"], "InProgress");
+        assert_eq!(
+            state
+                .handle("GET", "/lol-champ-select/v1/session", Value::Null)
+                .0,
+            404
+        );
+
+        let path = "/lol-matchmaking/v1/ready-check/accept";
+        assert_eq!(state.handle("POST", path, Value::Null).0, 204);
+        assert_eq!(state.phase, "ChampSelect");
+        let (status, session) = state.handle("GET", "/lol-champ-select/v1/session", Value::Null);
+        assert_eq!(status, 200);
+        assert_eq!(session["myTeam"][0]["summonerId"], 1);
+        // nothing left to accept
+        assert_eq!(state.handle("POST", path, Value::Null).0, 409);
+    }
+
+    #[test]
+    fn ready_check_is_declined_or_times_out() {
+        let mut state = ready_check();
+        let path = "/lol-matchmaking/v1/ready-check/decline";
+        assert_eq!(state.handle("POST", path, Value::Null).0, 204);
+        assert_eq!(state.phase, "Lobby");
+        assert_eq!(state.handle("POST", path, Value::Null).0, 409);
+
+        let mut state = ready_check();
+        let ready_check_time = state.script.ready_check_time;
+        rewind(&mut state, ready_check_time);
+        state.tick();
+        assert_eq!(state.phase, "Matchmaking");
+    }
+
+    #[test]
+    fn gameflow_follows_the_script() {
+        let mut state = ready_check();
+        state.handle(
+            "POST",
+            "/lol-matchmaking/v1/ready-check/accept",
+            Value::Null,
+        );
+        let mut phases = vec![];
+        for _ in 0..4 {
+            rewind(&mut state, 1000);
+            state.tick();
+            let (_, phase) = state.handle("GET", "/lol-gameflow/v1/gameflow-phase", Value::Null);
+            phases.push(phase);
+        }
+        assert_eq!(
+            phases,
+            ["InProgress", "WaitingForStats", "EndOfGame", "Lobby"]
+        );
+
+        let (status, session) = state.handle("GET", "/lol-gameflow/v1/session", Value::Null);
+        assert_eq!(status, 200);
+        assert_eq!(session["phase"], "Lobby");
+        assert_eq!(session["gameData"]["queue"]["id"], 400);
+        let sessions = published(&mut state, routes::game_flow::SESSION);
+        assert_eq!(sessions.last().unwrap().1["phase"], "Lobby");
+    }
+
+    #[test]
+    fn dodging_returns_to_the_lobby() {
+        let mut state = ready_check();
+        state.handle(
+            "POST",
+            "/lol-matchmaking/v1/ready-check/accept",
+            Value::Null,
+        );
+        state.events.clear();
+        state.handle("POST", "/lol-login/v1/session/invoke", Value::Null);
+        assert_eq!(state.phase, "Lobby");
+        let events = published(&mut state, champ_select::SESSION);
+        assert_eq!(events, [("Delete", Value::Null)]);
+    }
+
+    #[test]
+    fn ranked_stats_are_stable_per_puuid() {
+        let mut state = quiet();
+        let path = "/lol-ranked/v1/ranked-stats/mock-puuid-2";
+        let (status, first) = state.handle("GET", path, Value::Null);
+        assert_eq!(status, 200);
+        assert_eq!(state.handle("GET", path, Value::Null).1, first);
+        let solo = &first["queueMap"]["RANKED_SOLO_5x5"];
+        assert_eq!(solo["queueType"], "RANKED_SOLO_5x5");
+        assert!(solo["tier"].is_string());
+        assert!(first["queueMap"]["RANKED_FLEX_SR"].is_object());
+    }
+
+    #[test]
+    fn direct_messages_are_answered() {
+        let mut state = quiet();
+        let id = "mock-puuid-2@eu1.pvp.net";
+        let escaped = chat::escape(id);
+        let (status, conversation) =
+            state.handle("POST", "/lol-chat/v1/conversations", json!({ "id": id }));
+        assert_eq!(status, 200);
+        assert_eq!(conversation["type"], chat::DIRECT);
+        assert_eq!(conversation["name"], "Teemo Main");
+
+        let path = format!("/lol-chat/v1/conversations/{}/messages", escaped);
+        let (status, message) = state.handle("POST", &path, json!({ "body": "hi" }));
+        assert_eq!(status, 200);
+        assert_eq!(message["body"], "hi");
+        let (_, messages) = state.handle("GET", &path, Value::Null);
+        let bodies: Vec<&str> = messages
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|message| message["body"].as_str().unwrap())
+            .collect();
+        assert_eq!(bodies, ["hi", "lol"]);
+
+        let (_, conversations) = state.handle("GET", "/lol-chat/v1/conversations", Value::Null);
+        assert_eq!(conversations.as_array().unwrap().len(), 1);
+        let unknown = json!({ "id": "nobody@eu1.pvp.net" });
+        assert_eq!(
+            state
+                .handle("POST", "/lol-chat/v1/conversations", unknown)
+                .0,
+            404
+        );
+    }
+
+    #[test]
+    fn lobby_chat_opens_with_the_lobby() {
+        let mut state = quiet();
+        let path = format!(
+            "/lol-chat/v1/conversations/{}/messages",
+            chat::escape(LOBBY_ROOM)
+        );
+        assert_eq!(state.handle("POST", &path, json!({ "body": "gl" })).0, 404);
+
+        state.handle("POST", "/lol-lobby/v2/lobby", json!({ "queueId": 400 }));
+        let (_, conversations) = state.handle("GET", "/lol-chat/v1/conversations", Value::Null);
+        assert_eq!(conversations[0]["id"], LOBBY_ROOM);
+        assert_eq!(conversations[0]["type"], chat::LOBBY);
+        assert_eq!(state.handle("POST", &path, json!({ "body": "gl" })).0, 200);
+
+        state.handle("DELETE", "/lol-lobby/v2/lobby", Value::Null);
+        let (_, messages) = state.handle("GET", &path, Value::Null);
+        assert_eq!(messages, json!([]));
+    }
+
+    #[test]
+    fn friends_decode_like_the_clients() {
+        let mut state = quiet();
+        let (_, friends) = state.handle("GET", "/lol-chat/v1/friends", Value::Null);
+        let friends: Vec<kassadin::types::lcu::chat::Friend> =
+            serde_json::from_value(friends).unwrap();
+        assert_eq!(friends.len(), 4);
+        assert_eq!(friends[3].group_name, "Duo");
+    }
+
+    #[test]
+    fn unknown_routes_are_not_found() {
+        let mut state = quiet();
+        assert_eq!(
+            state.handle("GET", "/lol-nope/v1/thing", Value::Null).0,
+            404
+        );
+        assert_eq!(
+            state.handle("PATCH", "/lol-lobby/v2/lobby", Value::Null).0,
+            404
+        );
+    }
+}
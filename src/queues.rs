@@ -0,0 +1,270 @@
+//! The queues the client offers, from `/lol-game-queues/v1/queues`, and whether we may join them.
+//! Queues are numbered the way the client does it, `QueueId` only knows a few of them.
+
+use kassadin::types::lcu::consts::QueueId;
+use serde::Deserialize;
+use std::collections::HashMap;
+
+pub const QUEUES: &str = "/lol-game-queues/v1/queues";
+pub const ELIGIBILITY: &str = "/lol-lobby/v2/eligibility/game-select-eligibility-self";
+
+/// The queues kassadin has a `QueueId` for, also what we offer until the list is loaded.
+pub const KNOWN: [(QueueId, i64, &str); 6] = [
+    (QueueId::Solo, 420, "Solo"),
+    (QueueId::Draft, 400, "Draft"),
+    (QueueId::Flex, 440, "Flex"),
+    (QueueId::Blind, 430, "Blind"),
+    (QueueId::Clash, 700, "Clash"),
+    (QueueId::Aram, 450, "Aram"),
+];
+
+pub fn known(id: i64) -> Option<QueueId> {
+    KNOWN
+        .iter()
+        .find(|(_, known, _)| *known == id)
+        .map(|(queue_id, _, _)| *queue_id)
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct GameQueue {
+    pub id: i64,
+    pub name: String,
+    pub description: String,
+    /// `PvP`, `VersusAi` or `Custom`
+    pub category: String,
+    /// e.g. `CLASSIC`, `ARAM`, `URF` or `TFT`
+    pub game_mode: String,
+    pub map_id: i64,
+    /// `Available`, otherwise why not, e.g. `PlatformDisabled`
+    pub queue_availability: String,
+    pub is_ranked: bool,
+    pub min_level: i64,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct QueueEligibility {
+    pub queue_id: i64,
+    pub eligible: bool,
+    pub restrictions: Vec<QueueRestriction>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct QueueRestriction {
+    /// e.g. `PlayerMinLevelRestriction`
+    pub restriction_code: String,
+}
+
+impl GameQueue {
+    /// The section of the game select the queue is shown in.
+    pub fn group(&self) -> &'static str {
+        match (self.category.as_str(), self.game_mode.as_str()) {
+            (_, "TFT") => "Teamfight Tactics",
+            ("VersusAi", _) => "Co-op vs AI",
+            (_, "ARAM") => "ARAM",
+            (_, "CLASSIC") if self.map_id == 11 => "Summoner's Rift",
+            _ => "Featured",
+        }
+    }
+
+    /// The name the game select shows, the client's is empty for some rotating modes.
+    pub fn label(&self) -> &str {
+        if self.name.is_empty() {
+            &self.description
+        } else {
+            &self.name
+        }
+    }
+}
+
+/// Order of the groups in the game select.
+pub const GROUPS: [&str; 5] = [
+    "Summoner's Rift",
+    "ARAM",
+    "Featured",
+    "Co-op vs AI",
+    "Teamfight Tactics",
+];
+
+#[derive(Debug, Default)]
+pub struct QueueList {
+    /// empty until loaded
+    pub queues: Vec<GameQueue>,
+    /// by queue id, queues missing here count as eligible
+    pub eligibility: HashMap<i64, QueueEligibility>,
+}
+
+impl QueueList {
+    pub fn set(&mut self, mut queues: Vec<GameQueue>, eligibility: Vec<QueueEligibility>) {
+        // customs have their own flow and aren't offered
+        queues.retain(|queue| queue.category != "Custom");
+        queues.sort_by_key(|queue| queue.id);
+        self.queues = queues;
+        self.eligibility = eligibility
+            .into_iter()
+            .map(|eligibility| (eligibility.queue_id, eligibility))
+            .collect();
+    }
+
+    pub fn is_loaded(&self) -> bool {
+        !self.queues.is_empty()
+    }
+
+    pub fn get(&self, id: i64) -> Option<&GameQueue> {
+        self.queues.iter().find(|queue| queue.id == id)
+    }
+
+    /// The queues of `group`, in the client's order.
+    pub fn group<'a>(&'a self, group: &'a str) -> impl Iterator<Item = &'a GameQueue> + 'a {
+        self.queues
+            .iter()
+            .filter(move |queue| queue.group() == group)
+    }
+
+    /// A name for `id` even if the list isn't loaded, e.g. for the auto accept log.
+    pub fn name(&self, id: i64) -> String {
+        match (
+            self.get(id),
+            KNOWN.iter().find(|(_, known, _)| *known == id),
+        ) {
+            (Some(queue), _) => queue.label().to_string(),
+            (None, Some((_, _, name))) => name.to_string(),
+            (None, None) => format!("Queue {}", id),
+        }
+    }
+
+    /// Why `queue` can't be joined right now, `None` if it can.
+    pub fn unavailable(&self, queue: &GameQueue) -> Option<String> {
+        if queue.queue_availability != "Available" {
+            return Some(match queue.queue_availability.as_str() {
+                "PlatformDisabled" => "Disabled by the client".to_string(),
+                other => other.to_string(),
+            });
+        }
+        let eligibility = self.eligibility.get(&queue.id)?;
+        if eligibility.eligible {
+            return None;
+        }
+        let reasons = eligibility
+            .restrictions
+            .iter()
+            .map(|restriction| match restriction.restriction_code.as_str() {
+                "PlayerMinLevelRestriction" => format!("Needs level {}", queue.min_level),
+                "PlayerMaxLevelRestriction" => "Level too high".to_string(),
+                "PlayerRankedSuspensionRestriction" | "PlayerBannedRestriction" => {
+                    "Suspended from this queue".to_string()
+                }
+                "PlayerMinorRestriction" => "Not allowed for minors".to_string(),
+                "PlayerTimedRestriction" | "PlayerLeaverBusterLowPriorityRestriction" => {
+                    "Queue restricted by penalties".to_string()
+                }
+                "PlayerInGameRestriction" => "Already in a game".to_string(),
+                "TeamDivisionRestriction" | "TeamSkillRestriction" => {
+                    "Party ranks too far apart".to_string()
+                }
+                "TeamSizeRestriction" | "TeamMaxSizeRestriction" | "TeamMinSizeRestriction" => {
+                    "Wrong party size".to_string()
+                }
+                "PlayerAvailableChampionRestriction" => "Not enough champions".to_string(),
+                "QueueDisabled" | "QueueUnsupported" => "Disabled by the client".to_string(),
+                other => other.to_string(),
+            })
+            .collect::<Vec<_>>();
+        if reasons.is_empty() {
+            Some("Not eligible".to_string())
+        } else {
+            Some(reasons.join(", "))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    /// Cut down from `/lol-game-queues/v1/queues` and the eligibility, fields we don't read left out.
+    fn queue_list() -> QueueList {
+        let queues = json!([
+            {"id": 450, "name": "ARAM", "category": "PvP", "gameMode": "ARAM", "mapId": 12,
+             "queueAvailability": "Available", "minLevel": 0},
+            {"id": 420, "name": "Ranked Solo/Duo", "category": "PvP", "gameMode": "CLASSIC",
+             "mapId": 11, "queueAvailability": "Available", "isRanked": true, "minLevel": 30},
+            {"id": 830, "name": "Intro", "category": "VersusAi", "gameMode": "CLASSIC",
+             "mapId": 11, "queueAvailability": "Available"},
+            {"id": 1090, "name": "", "description": "Teamfight Tactics", "category": "PvP",
+             "gameMode": "TFT", "mapId": 22, "queueAvailability": "PlatformDisabled"},
+            {"id": 900, "name": "ARURF", "category": "PvP", "gameMode": "URF", "mapId": 11,
+             "queueAvailability": "Available"},
+            {"id": 3140, "name": "Practice Tool", "category": "Custom", "gameMode": "PRACTICETOOL",
+             "mapId": 11, "queueAvailability": "Available"},
+        ]);
+        let eligibility = json!([
+            {"queueId": 420, "eligible": false, "restrictions": [
+                {"restrictionCode": "PlayerMinLevelRestriction", "restrictedAccountIds": [1]},
+                {"restrictionCode": "TeamSizeRestriction"},
+            ]},
+            {"queueId": 450, "eligible": true, "restrictions": []},
+            {"queueId": 900, "eligible": false, "restrictions": [
+                {"restrictionCode": "SomethingNew"},
+            ]},
+            {"queueId": 830, "eligible": false, "restrictions": []},
+        ]);
+        let mut list = QueueList::default();
+        list.set(
+            serde_json::from_value(queues).unwrap(),
+            serde_json::from_value(eligibility).unwrap(),
+        );
+        list
+    }
+
+    fn unavailable(list: &QueueList, id: i64) -> Option<String> {
+        list.unavailable(list.get(id).unwrap())
+    }
+
+    #[test]
+    fn queues_are_grouped_in_order_without_customs() {
+        let list = queue_list();
+        assert!(list.is_loaded());
+        assert!(list.get(3140).is_none());
+        let groups = GROUPS
+            .iter()
+            .map(|group| list.group(group).map(|queue| queue.id).collect::<Vec<_>>())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            groups,
+            [vec![420], vec![450], vec![900], vec![830], vec![1090]]
+        );
+        assert_eq!(list.get(1090).unwrap().label(), "Teamfight Tactics");
+    }
+
+    #[test]
+    fn restrictions_say_why_a_queue_cant_be_joined() {
+        let list = queue_list();
+        assert_eq!(
+            unavailable(&list, 420).as_deref(),
+            Some("Needs level 30, Wrong party size")
+        );
+        assert_eq!(unavailable(&list, 450), None);
+        assert_eq!(unavailable(&list, 900).as_deref(), Some("SomethingNew"));
+        assert_eq!(unavailable(&list, 830).as_deref(), Some("Not eligible"));
+        assert_eq!(
+            unavailable(&list, 1090).as_deref(),
+            Some("Disabled by the client")
+        );
+    }
+
+    #[test]
+    fn known_queues_have_names_before_the_list_loads() {
+        assert_eq!(known(420), Some(QueueId::Solo));
+        assert_eq!(known(450), Some(QueueId::Aram));
+        assert_eq!(known(900), None);
+
+        let empty = QueueList::default();
+        assert_eq!(empty.name(440), "Flex");
+        assert_eq!(empty.name(900), "Queue 900");
+        assert_eq!(queue_list().name(420), "Ranked Solo/Duo");
+    }
+}
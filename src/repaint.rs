@@ -0,0 +1,170 @@
+//! Repaint when something happened instead of on a fixed interval:
+//! every incoming `Event` wakes the ui, and running clocks ask for the next frame
+//! when their shown value changes. Input is handled by eframe itself.
+//!
+//! Nothing in here knows about windows, `wake` is whatever makes the next frame happen.
+
+use crossbeam::channel::{Receiver, RecvTimeoutError, Sender};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+pub type Wake = Arc<dyn Fn() + Send + Sync>;
+
+/// Forward everything from `events` to the returned receiver, waking the ui for each.
+pub fn relay<T: Send + 'static>(events: Receiver<T>, wake: Wake) -> Receiver<T> {
+    let (send, recv) = crossbeam::channel::unbounded();
+    std::thread::spawn(move || {
+        for event in events {
+            if send.send(event).is_err() {
+                return;
+            }
+            wake();
+        }
+    });
+    recv
+}
+
+/// Wakes the ui at requested points in time, and not at all if nothing was requested.
+pub struct Repainter {
+    schedule: Sender<Instant>,
+}
+
+impl Repainter {
+    pub fn spawn(wake: Wake) -> Self {
+        let (schedule, requests) = crossbeam::channel::unbounded();
+        std::thread::spawn(move || run(requests, wake));
+        Self { schedule }
+    }
+
+    pub fn repaint_at(&self, at: Instant) {
+        let _ = self.schedule.send(at);
+    }
+}
+
+fn run(requests: Receiver<Instant>, wake: Wake) {
+    let mut next: Option<Instant> = None;
+    loop {
+        let request = match next {
+            Some(at) => requests.recv_deadline(at),
+            None => requests.recv().map_err(|_| RecvTimeoutError::Disconnected),
+        };
+        match request {
+            Ok(at) => next = earliest(next, Some(at)),
+            Err(RecvTimeoutError::Timeout) => {
+                next = None;
+                wake();
+            }
+            Err(RecvTimeoutError::Disconnected) => return,
+        }
+    }
+}
+
+pub fn earliest(a: Option<Instant>, b: Option<Instant>) -> Option<Instant> {
+    match (a, b) {
+        (Some(a), Some(b)) => Some(a.min(b)),
+        (a, None) => a,
+        (None, b) => b,
+    }
+}
+
+/// When a countdown showing whole seconds with `left` remaining changes its text next,
+/// `None` once it ran out.
+pub fn next_second(now: Instant, left: Duration) -> Option<Instant> {
+    if left.is_zero() {
+        return None;
+    }
+    let step = match left.subsec_nanos() {
+        0 => Duration::from_secs(1),
+        fraction => Duration::from_nanos(fraction as u64),
+    };
+    Some(now + step)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WAIT: Duration = Duration::from_secs(2);
+
+    /// A `Repainter` whose wakes arrive on the returned receiver.
+    fn repainter() -> (Repainter, Receiver<Instant>) {
+        let (woke, wakes) = crossbeam::channel::unbounded();
+        let wake: Wake = Arc::new(move || {
+            let _ = woke.send(Instant::now());
+        });
+        (Repainter::spawn(wake), wakes)
+    }
+
+    #[test]
+    fn next_second_of_whole_seconds_is_a_second_away() {
+        let now = Instant::now();
+        assert_eq!(
+            next_second(now, Duration::from_secs(3)),
+            Some(now + Duration::from_secs(1))
+        );
+    }
+
+    #[test]
+    fn next_second_drops_the_fraction_first() {
+        let now = Instant::now();
+        assert_eq!(
+            next_second(now, Duration::from_millis(2250)),
+            Some(now + Duration::from_millis(250))
+        );
+    }
+
+    #[test]
+    fn next_second_stops_at_zero() {
+        assert_eq!(next_second(Instant::now(), Duration::ZERO), None);
+    }
+
+    #[test]
+    fn earliest_takes_the_sooner_deadline() {
+        let now = Instant::now();
+        let later = now + Duration::from_secs(1);
+        assert_eq!(earliest(None, None), None);
+        assert_eq!(earliest(Some(now), None), Some(now));
+        assert_eq!(earliest(None, Some(now)), Some(now));
+        assert_eq!(earliest(Some(later), Some(now)), Some(now));
+
+        let deadlines = [
+            Some(later),
+            None,
+            Some(now + Duration::from_millis(10)),
+            None,
+        ];
+        assert_eq!(
+            deadlines.iter().copied().fold(None, earliest),
+            Some(now + Duration::from_millis(10))
+        );
+    }
+
+    #[test]
+    fn repainter_wakes_at_the_requested_time() {
+        let (repainter, wakes) = repainter();
+        let at = Instant::now() + Duration::from_millis(50);
+        repainter.repaint_at(at);
+
+        let woke = wakes.recv_timeout(WAIT).expect("no wake");
+        assert!(woke >= at);
+        // one request, one wake
+        assert!(wakes.recv_timeout(Duration::from_millis(100)).is_err());
+    }
+
+    #[test]
+    fn repainter_sleeps_without_requests() {
+        let (_repainter, wakes) = repainter();
+        assert!(wakes.recv_timeout(Duration::from_millis(100)).is_err());
+    }
+
+    #[test]
+    fn repainter_wakes_for_the_earliest_request() {
+        let (repainter, wakes) = repainter();
+        let start = Instant::now();
+        repainter.repaint_at(start + Duration::from_secs(60));
+        repainter.repaint_at(start + Duration::from_millis(50));
+
+        let woke = wakes.recv_timeout(WAIT).expect("no wake");
+        assert!(woke < start + Duration::from_secs(60));
+    }
+}
@@ -0,0 +1,371 @@
+//! Maps websocket event URIs to typed decoders.
+//! Register a pattern and the kind its `data` decodes into, the socket loop doesn't need to change:
+//!
+//! * `/lol-lobby/v2/lobby` matches exactly that uri
+//! * `/lol-chat/v1/*` matches everything starting with `/lol-chat/v1/`
+//! * `/lol-chat/v1/friends/{id}` matches one segment per placeholder and captures it
+
+use crate::error::{Error, Report};
+use crate::event::{EventKind, SocketEvent};
+use crate::logging;
+use crate::ui::account::{self, ChatMe};
+use crate::ui::chat::{self, ChatMessage, Conversation};
+use crate::ui::friendlist::groups;
+use crate::ui::game::champ_select::{self, ChampSelectSession};
+use crate::ui::game::invitations::{self, ReceivedInvitation};
+use crate::ui::game::lobby::LobbySession;
+use kassadin::routes;
+use kassadin::types::socket::{EventType, FriendEvent, GameFlowEvent, LeagueEventKind, QueueEvent};
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+pub type Params = HashMap<String, String>;
+
+/// A uri whose events don't decode usually keeps failing, it's logged once per interval.
+const DECODE_LOG_INTERVAL: Duration = Duration::from_secs(60);
+
+type Decoder = Box<dyn Fn(&str, &Params, &Value) -> (EventKind, Option<Report>) + Send + Sync>;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum UriPattern {
+    Exact(String),
+    Prefix(String),
+    Template(Vec<Segment>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Segment {
+    Literal(String),
+    Param(String),
+}
+
+impl UriPattern {
+    pub fn parse(pattern: &str) -> Self {
+        if let Some(prefix) = pattern.strip_suffix('*') {
+            Self::Prefix(prefix.to_string())
+        } else if pattern.contains('{') {
+            let segments = pattern
+                .trim_start_matches('/')
+                .split('/')
+                .map(|segment| {
+                    match segment
+                        .strip_prefix('{')
+                        .and_then(|segment| segment.strip_suffix('}'))
+                    {
+                        Some(name) => Segment::Param(name.to_string()),
+                        None => Segment::Literal(segment.to_string()),
+                    }
+                })
+                .collect();
+            Self::Template(segments)
+        } else {
+            Self::Exact(pattern.to_string())
+        }
+    }
+
+    /// The captured placeholders if `uri` matches.
+    pub fn matches(&self, uri: &str) -> Option<Params> {
+        match self {
+            Self::Exact(pattern) => (pattern == uri).then(Params::new),
+            Self::Prefix(prefix) => uri.starts_with(prefix.as_str()).then(Params::new),
+            Self::Template(segments) => {
+                let parts: Vec<&str> = uri.trim_start_matches('/').split('/').collect();
+                if parts.len() != segments.len() {
+                    return None;
+                }
+                let mut params = Params::new();
+                for (segment, part) in segments.iter().zip(parts) {
+                    match segment {
+                        Segment::Literal(literal) if literal == part => {}
+                        Segment::Literal(_) => return None,
+                        Segment::Param(name) => {
+                            params.insert(name.clone(), part.to_string());
+                        }
+                    }
+                }
+                Some(params)
+            }
+        }
+    }
+}
+
+/// Routes are tried in the order they were registered, the first match wins.
+/// Uris no route matches become `EventKind::Unknown`.
+#[derive(Default)]
+pub struct Router {
+    routes: Vec<(UriPattern, Decoder)>,
+    /// when a decode error was last logged and how many were skipped since, by uri
+    decode_errors: Mutex<HashMap<String, (Instant, u32)>>,
+}
+
+impl Router {
+    /// Every uri Clowncher understands.
+    pub fn league() -> Self {
+        Self::default()
+            .route::<QueueEvent, _>(routes::matchmaking::SEARCH, |queue| {
+                EventKind::League(Box::new(LeagueEventKind::Queue(queue)))
+            })
+            .route::<QueueEvent, _>(routes::lobby::team_builder::MATCHMAKING, |queue| {
+                EventKind::League(Box::new(LeagueEventKind::Queue(queue)))
+            })
+            .route::<GameFlowEvent, _>(routes::game_flow::SESSION, |flow| {
+                EventKind::League(Box::new(LeagueEventKind::GameFlow(flow)))
+            })
+            .route::<LobbySession, _>(routes::lobby::LOBBY, EventKind::Lobby)
+            .route::<Vec<ReceivedInvitation>, _>(
+                invitations::RECEIVED_INVITATIONS,
+                EventKind::Invitations,
+            )
+            .route::<FriendEvent, _>(&format!("{}/{{id}}", routes::chat::FRIENDS), |friend| {
+                EventKind::League(Box::new(LeagueEventKind::Friend(friend)))
+            })
+            .route::<Value, _>(&format!("{}*", groups::FRIEND_GROUPS), |_| {
+                EventKind::FriendGroups
+            })
+            .route::<ChampSelectSession, _>(champ_select::SESSION, EventKind::ChampSelect)
+            .route::<ChatMe, _>(account::ME, EventKind::Me)
+            .route::<Vec<Conversation>, _>(chat::CONVERSATIONS, EventKind::Conversations)
+            .route_params::<Conversation, _>(
+                &format!("{}/{{id}}", chat::CONVERSATIONS),
+                |params, conversation| {
+                    EventKind::Conversation(chat::unescape(&params["id"]), conversation)
+                },
+            )
+            .route_params::<ChatMessage, _>(
+                &format!("{}/{{id}}/messages/{{message}}", chat::CONVERSATIONS),
+                |params, message| EventKind::ChatMessage(chat::unescape(&params["id"]), message),
+            )
+    }
+
+    /// Decode `data` of events matching `pattern` into `T`, `None` if it doesn't fit (e.g. on deletes).
+    pub fn route<T, F>(self, pattern: &str, kind: F) -> Self
+    where
+        T: DeserializeOwned + 'static,
+        F: Fn(Option<T>) -> EventKind + Send + Sync + 'static,
+    {
+        self.route_params::<T, _>(pattern, move |_params, data| kind(data))
+    }
+
+    /// Like `route`, but also hands over the placeholders captured from the uri.
+    pub fn route_params<T, F>(mut self, pattern: &str, kind: F) -> Self
+    where
+        T: DeserializeOwned + 'static,
+        F: Fn(&Params, Option<T>) -> EventKind + Send + Sync + 'static,
+    {
+        let pattern = UriPattern::parse(pattern);
+        let decoder = move |uri: &str, params: &Params, data: &Value| {
+            match serde_json::from_value::<T>(data.clone()) {
+                Ok(data) => (kind(params, Some(data)), None),
+                // deletes come without data, that's not an error
+                Err(_) if data.is_null() => (kind(params, None), None),
+                Err(source) => {
+                    let error = Error::Decode {
+                        uri: uri.to_string(),
+                        source,
+                    };
+                    (kind(params, None), Some(Report::new(error)))
+                }
+            }
+        };
+        self.routes.push((pattern, Box::new(decoder)));
+        self
+    }
+
+    /// Decode a raw `OnJsonApiEvent` frame, `None` if it isn't one.
+    pub fn dispatch(&self, message: &str) -> Option<SocketEvent> {
+        let val = serde_json::from_str::<Value>(message).ok()?;
+        let payload = &val[2];
+        let uri = payload.get("uri")?.as_str()?;
+        let event_type = serde_json::from_value::<EventType>(payload["eventType"].clone()).ok();
+        Some(self.decode(uri, event_type, &payload["data"]))
+    }
+
+    /// Decode `data` as if it arrived for `uri`, e.g. a REST response for the same resource.
+    pub fn decode(&self, uri: &str, event_type: Option<EventType>, data: &Value) -> SocketEvent {
+        let (kind, error) = self
+            .routes
+            .iter()
+            .find_map(|(pattern, decoder)| {
+                pattern
+                    .matches(uri)
+                    .map(|params| decoder(uri, &params, data))
+            })
+            .unwrap_or_else(|| (EventKind::Unknown(data.clone()), None));
+        if let Some(error) = &error {
+            self.log_decode_error(uri, error);
+        }
+
+        SocketEvent {
+            uri: uri.to_string(),
+            event_type,
+            kind,
+            error,
+        }
+    }
+
+    fn log_decode_error(&self, uri: &str, error: &Report) {
+        let mut decode_errors = self.decode_errors.lock().unwrap();
+        let now = Instant::now();
+        match decode_errors.get_mut(uri) {
+            Some((logged, skipped)) if now.duration_since(*logged) < DECODE_LOG_INTERVAL => {
+                *skipped += 1;
+            }
+            entry => {
+                let skipped = entry.map_or(0, |(_, skipped)| *skipped);
+                log::warn!(
+                    target: logging::SOCKET,
+                    "{:?} ({} more since last logged)",
+                    error,
+                    skipped
+                );
+                decode_errors.insert(uri.to_string(), (now, 0));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn params(pairs: &[(&str, &str)]) -> Params {
+        pairs
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect()
+    }
+
+    /// Routes that tell which one matched through the `Unknown` they produce.
+    fn named(router: Router, pattern: &'static str) -> Router {
+        router.route_params::<Value, _>(pattern, move |params, _| {
+            EventKind::Unknown(json!({ "route": pattern, "params": params }))
+        })
+    }
+
+    fn matched(router: &Router, uri: &str) -> Value {
+        match router.decode(uri, Some(EventType::Update), &json!({})).kind {
+            EventKind::Unknown(value) => value,
+            kind => panic!("unexpected {:?}", kind),
+        }
+    }
+
+    #[test]
+    fn patterns_are_parsed_by_shape() {
+        assert_eq!(
+            UriPattern::parse("/lol-lobby/v2/lobby"),
+            UriPattern::Exact("/lol-lobby/v2/lobby".to_string())
+        );
+        assert_eq!(
+            UriPattern::parse("/lol-chat/v1/*"),
+            UriPattern::Prefix("/lol-chat/v1/".to_string())
+        );
+        assert_eq!(
+            UriPattern::parse("/lol-chat/v1/friends/{id}"),
+            UriPattern::Template(vec![
+                Segment::Literal("lol-chat".to_string()),
+                Segment::Literal("v1".to_string()),
+                Segment::Literal("friends".to_string()),
+                Segment::Param("id".to_string()),
+            ])
+        );
+    }
+
+    #[test]
+    fn exact_matches_only_the_uri() {
+        let pattern = UriPattern::parse("/lol-lobby/v2/lobby");
+        assert_eq!(pattern.matches("/lol-lobby/v2/lobby"), Some(Params::new()));
+        assert_eq!(pattern.matches("/lol-lobby/v2/lobby/members"), None);
+        assert_eq!(pattern.matches("/lol-lobby/v2"), None);
+    }
+
+    #[test]
+    fn prefix_matches_everything_below() {
+        let pattern = UriPattern::parse("/lol-chat/v1/*");
+        assert_eq!(pattern.matches("/lol-chat/v1/friends"), Some(Params::new()));
+        assert_eq!(
+            pattern.matches("/lol-chat/v1/conversations/a/messages"),
+            Some(Params::new())
+        );
+        assert_eq!(pattern.matches("/lol-chat/v2/friends"), None);
+    }
+
+    #[test]
+    fn template_captures_params() {
+        let pattern = UriPattern::parse("/lol-chat/v1/conversations/{id}/messages/{message}");
+        assert_eq!(
+            pattern.matches("/lol-chat/v1/conversations/abc/messages/42"),
+            Some(params(&[("id", "abc"), ("message", "42")]))
+        );
+        assert_eq!(
+            pattern.matches("/lol-chat/v1/conversations/abc/participants/42"),
+            None
+        );
+    }
+
+    #[test]
+    fn template_needs_the_same_segment_count() {
+        let pattern = UriPattern::parse("/lol-chat/v1/friends/{id}");
+        assert_eq!(pattern.matches("/lol-chat/v1/friends"), None);
+        assert_eq!(pattern.matches("/lol-chat/v1/friends/a/b"), None);
+        assert_eq!(
+            pattern.matches("/lol-chat/v1/friends/a"),
+            Some(params(&[("id", "a")]))
+        );
+    }
+
+    #[test]
+    fn first_match_wins() {
+        let router = named(Router::default(), "/lol-chat/v1/friends/{id}");
+        let router = named(router, "/lol-chat/v1/*");
+        let router = named(router, "/lol-chat/v1/friends/abc");
+
+        let friend = matched(&router, "/lol-chat/v1/friends/abc");
+        assert_eq!(friend["route"], "/lol-chat/v1/friends/{id}");
+        assert_eq!(friend["params"]["id"], "abc");
+
+        let other = matched(&router, "/lol-chat/v1/session");
+        assert_eq!(other["route"], "/lol-chat/v1/*");
+    }
+
+    #[test]
+    fn unmatched_uris_carry_their_data() {
+        let router = named(Router::default(), "/lol-lobby/v2/lobby");
+        let event = router.decode("/riot/unknown", None, &json!({ "a": 1 }));
+        assert!(matches!(event.kind, EventKind::Unknown(data) if data == json!({ "a": 1 })));
+        assert!(event.error.is_none());
+    }
+
+    #[test]
+    fn dispatch_reads_json_api_events() {
+        let router = named(Router::default(), "/lol-lobby/v2/lobby");
+        let frame = json!([
+            8,
+            "OnJsonApiEvent",
+            { "uri": "/lol-lobby/v2/lobby", "eventType": "Delete", "data": null }
+        ]);
+        let event = router.dispatch(&frame.to_string()).unwrap();
+        assert_eq!(event.uri, "/lol-lobby/v2/lobby");
+        assert!(matches!(event.event_type, Some(EventType::Delete)));
+
+        assert!(router.dispatch("[5, \"OnJsonApiEvent\"]").is_none());
+        assert!(router.dispatch("not json").is_none());
+    }
+
+    #[test]
+    fn undecodable_data_is_an_error_but_null_is_not() {
+        let router = Router::default()
+            .route::<Vec<i64>, _>("/numbers", |numbers| EventKind::Unknown(json!(numbers)));
+
+        let event = router.decode("/numbers", None, &json!("three"));
+        assert!(matches!(event.kind, EventKind::Unknown(Value::Null)));
+        assert!(event.error.is_some());
+
+        let event = router.decode("/numbers", Some(EventType::Delete), &Value::Null);
+        assert!(matches!(event.kind, EventKind::Unknown(Value::Null)));
+        assert!(event.error.is_none());
+    }
+}
@@ -1,2 +1,30 @@
+use crate::ui::friendlist::Status;
+use serde::Deserialize;
+
+pub const ME: &str = "/lol-chat/v1/me";
+
+/// The local player as the chat sees them.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChatMe {
+    pub name: Option<String>,
+    pub availability: Option<String>,
+}
+
 #[derive(Debug, Default)]
-pub struct Account {}
+pub struct Account {
+    pub name: Option<String>,
+    /// `None` until the chat told us
+    pub status: Option<Status>,
+}
+
+impl Account {
+    pub fn update(&mut self, me: ChatMe) {
+        self.name = me.name;
+        self.status = Some(Status::from(me.availability));
+    }
+
+    pub fn is_away(&self) -> bool {
+        self.status == Some(Status::Away)
+    }
+}
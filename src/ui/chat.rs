@@ -0,0 +1,454 @@
+//! Conversations from `/lol-chat/v1/conversations`: the lobby and champ select rooms and direct messages.
+//! Messages arrive as websocket events per conversation, `Command::LoadConversation` loads the history.
+//! Direct messages only load it once their window is opened, there can be hundreds of them.
+
+use crate::command::{Command, CommandQueue};
+use chrono::{DateTime, Local};
+use eframe::egui;
+use serde::Deserialize;
+use std::collections::HashMap;
+
+pub const CONVERSATIONS: &str = "/lol-chat/v1/conversations";
+
+/// conversation types, the lobby is a custom game room to the chat
+pub const LOBBY: &str = "customGame";
+pub const CHAMP_SELECT: &str = "championSelect";
+/// direct messages, the conversation id is the friend's chat id
+pub const DIRECT: &str = "chat";
+
+/// messages kept per conversation, older ones are dropped
+const MAX_MESSAGES: usize = 200;
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct Conversation {
+    pub id: String,
+    #[serde(rename = "type")]
+    pub kind: String,
+    pub name: String,
+    pub unread_message_count: i64,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ChatMessage {
+    pub id: String,
+    pub body: String,
+    /// the sender's chat id
+    pub from_id: String,
+    pub from_summoner_id: i64,
+    /// rfc3339
+    pub timestamp: String,
+    /// `chat`, `groupchat`, `system` or `celebration`
+    #[serde(rename = "type")]
+    pub kind: String,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct Participant {
+    pub name: String,
+    pub game_name: String,
+    pub summoner_id: i64,
+}
+
+impl ChatMessage {
+    pub fn is_system(&self) -> bool {
+        self.kind == "system"
+    }
+
+    pub fn time(&self) -> Option<DateTime<Local>> {
+        DateTime::parse_from_rfc3339(&self.timestamp)
+            .ok()
+            .map(|time| time.with_timezone(&Local))
+    }
+}
+
+/// The uri of a conversation, ids contain `@` and have to be escaped.
+pub fn conversation_uri(id: &str) -> String {
+    format!("{}/{}", CONVERSATIONS, escape(id))
+}
+
+/// A chat id as a uri segment, e.g. `abc@sec.pvp.net` as `abc%40sec.pvp.net`.
+pub fn escape(id: &str) -> String {
+    id.bytes()
+        .map(|byte| match byte {
+            b'a'..=b'z' | b'A'..=b'Z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                (byte as char).to_string()
+            }
+            _ => format!("%{:02X}", byte),
+        })
+        .collect()
+}
+
+/// A conversation id as it appears in event uris, e.g. `abc%40sec.pvp.net`, unescaped.
+pub fn unescape(id: &str) -> String {
+    let bytes = id.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = bytes
+            .get(i + 1..i + 3)
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match (bytes[i], hex) {
+            (b'%', Some(byte)) => {
+                out.push(byte);
+                i += 3;
+            }
+            (byte, _) => {
+                out.push(byte);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+#[derive(Debug)]
+pub struct Room {
+    pub conversation: Conversation,
+    pub messages: Vec<ChatMessage>,
+    /// by summoner id, from the participants and lobby members
+    pub names: HashMap<i64, String>,
+    pub draft: String,
+    /// set when messages arrived, the next frame scrolls down to them
+    scroll: bool,
+    /// whether the history was loaded
+    loaded: bool,
+    /// direct messages only, whether its window is shown
+    pub open: bool,
+    /// direct messages only, received while the window was closed
+    pub unread: usize,
+}
+
+impl Room {
+    fn new(conversation: Conversation) -> Self {
+        Self {
+            conversation,
+            messages: vec![],
+            names: HashMap::new(),
+            draft: String::new(),
+            scroll: true,
+            loaded: false,
+            open: false,
+            unread: 0,
+        }
+    }
+
+    fn is_direct(&self) -> bool {
+        self.conversation.kind == DIRECT
+    }
+
+    /// Add `message` unless we have it already, e.g. history overlapping live messages.
+    fn push(&mut self, message: ChatMessage) -> bool {
+        if self.messages.iter().any(|known| known.id == message.id) {
+            return false;
+        }
+        self.messages.push(message);
+        if self.messages.len() > MAX_MESSAGES {
+            self.messages.remove(0);
+        }
+        self.scroll = true;
+        true
+    }
+
+    /// Who sent `message`, in direct messages it's either the friend or us.
+    fn sender(&self, message: &ChatMessage) -> &str {
+        if !self.is_direct() {
+            return self.name(message.from_summoner_id);
+        }
+        if message.from_id == self.conversation.id {
+            &self.conversation.name
+        } else {
+            "You"
+        }
+    }
+
+    fn name(&self, summoner_id: i64) -> &str {
+        self.names
+            .get(&summoner_id)
+            .map_or("Someone", String::as_str)
+    }
+
+    /// e.g. `Teemo Main joined`, the client sends the joins and leaves as codes.
+    fn system_text(&self, message: &ChatMessage) -> String {
+        let name = self.name(message.from_summoner_id);
+        match message.body.as_str() {
+            "joined_room" => format!("{} joined", name),
+            "left_room" => format!("{} left", name),
+            body => body.to_string(),
+        }
+    }
+
+    /// The messages, a line to type in and Send.
+    pub fn ui(&mut self, ui: &mut egui::Ui, commands: &mut CommandQueue) {
+        egui::ScrollArea::vertical()
+            .id_source(&self.conversation.id)
+            .max_height(200.0)
+            .show(ui, |ui| {
+                for message in &self.messages {
+                    let time = message
+                        .time()
+                        .map_or(String::new(), |time| time.format("%H:%M").to_string());
+                    if message.is_system() {
+                        ui.label(
+                            egui::RichText::new(format!("{} {}", time, self.system_text(message)))
+                                .text_style(egui::TextStyle::Small)
+                                .color(crate::ui::colors::GRAY_A500),
+                        );
+                    } else {
+                        ui.label(format!(
+                            "{} {}: {}",
+                            time,
+                            self.sender(message),
+                            message.body
+                        ));
+                    }
+                }
+                if self.scroll {
+                    ui.scroll_to_cursor(egui::Align::BOTTOM);
+                    self.scroll = false;
+                }
+            });
+
+        ui.horizontal(|ui| {
+            let input = ui.text_edit_singleline(&mut self.draft);
+            let entered = input.lost_focus() && ui.input().key_pressed(egui::Key::Enter);
+            if (ui.button("Send").clicked() || entered) && !self.draft.trim().is_empty() {
+                let body = std::mem::take(&mut self.draft);
+                let kind = if self.is_direct() {
+                    "chat"
+                } else {
+                    "groupchat"
+                };
+                commands.send(Command::SendMessage(
+                    self.conversation.id.clone(),
+                    kind,
+                    body,
+                ));
+                input.request_focus();
+            }
+        });
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Chat {
+    rooms: HashMap<String, Room>,
+    next_note: u64,
+}
+
+impl Chat {
+    /// Whether Clowncher shows conversations of `kind`.
+    pub fn is_shown(kind: &str) -> bool {
+        matches!(kind, LOBBY | CHAMP_SELECT | DIRECT)
+    }
+
+    /// Track `conversation`, `true` if it's new and its history should be loaded now.
+    pub fn conversation(&mut self, conversation: Conversation) -> bool {
+        if !Self::is_shown(&conversation.kind) {
+            return false;
+        }
+        match self.rooms.get_mut(&conversation.id) {
+            Some(room) => {
+                // the client names direct conversations after the friend, keep ours if it doesn't
+                if room.is_direct() && conversation.name.is_empty() {
+                    return false;
+                }
+                room.conversation = conversation;
+                false
+            }
+            None => {
+                let mut room = Room::new(conversation);
+                // what the official client hasn't marked read yet
+                room.unread = room.conversation.unread_message_count.max(0) as usize;
+                let load = !room.is_direct();
+                self.rooms.insert(room.conversation.id.clone(), room);
+                load
+            }
+        }
+    }
+
+    /// Take the client's list, the ids returned are new.
+    pub fn set_conversations(&mut self, conversations: Vec<Conversation>) -> Vec<String> {
+        let ids = conversations
+            .iter()
+            .map(|conversation| conversation.id.clone())
+            .collect::<Vec<_>>();
+        self.rooms.retain(|id, _| ids.contains(id));
+        conversations
+            .into_iter()
+            .filter_map(|conversation| {
+                let id = conversation.id.clone();
+                self.conversation(conversation).then_some(id)
+            })
+            .collect()
+    }
+
+    pub fn remove(&mut self, id: &str) {
+        self.rooms.remove(id);
+    }
+
+    pub fn clear(&mut self) {
+        self.rooms.clear();
+    }
+
+    /// A live message, `false` if it's for a conversation we don't show or we have it already.
+    pub fn message(&mut self, id: &str, message: ChatMessage) -> bool {
+        let room = match self.rooms.get_mut(id) {
+            Some(room) => room,
+            None => return false,
+        };
+        let from_friend = room.is_direct() && message.from_id == room.conversation.id;
+        let new = room.push(message);
+        if new && from_friend && !room.open {
+            room.unread += 1;
+        }
+        new
+    }
+
+    /// Show the direct conversation with a friend, `true` if its history has to be loaded.
+    pub fn open_direct(&mut self, id: &str, name: &str) -> bool {
+        let room = self.rooms.entry(id.to_string()).or_insert_with(|| {
+            Room::new(Conversation {
+                id: id.to_string(),
+                kind: DIRECT.to_string(),
+                ..Default::default()
+            })
+        });
+        room.conversation.name = name.to_string();
+        room.open = true;
+        room.unread = 0;
+        !room.loaded
+    }
+
+    /// Unread direct messages from the friend with chat id `id`.
+    pub fn unread(&self, id: &str) -> usize {
+        self.rooms.get(id).map_or(0, |room| room.unread)
+    }
+
+    /// A window per opened direct conversation.
+    pub fn ui_direct(&mut self, ctx: &egui::CtxRef, commands: &mut CommandQueue) {
+        for room in self.rooms.values_mut().filter(|room| room.is_direct()) {
+            if !room.open {
+                continue;
+            }
+            let mut open = true;
+            egui::Window::new(&room.conversation.name)
+                .id(egui::Id::new("direct").with(&room.conversation.id))
+                .open(&mut open)
+                .resizable(false)
+                .show(ctx, |ui| {
+                    room.ui(ui, commands);
+                });
+            room.open = open;
+            // read while shown
+            room.unread = 0;
+        }
+    }
+
+    /// Whether a message in `id` comes from someone we have no name for.
+    pub fn is_stranger(&self, id: &str, summoner_id: i64) -> bool {
+        self.rooms
+            .get(id)
+            .is_some_and(|room| !room.names.contains_key(&summoner_id))
+    }
+
+    pub fn history(
+        &mut self,
+        id: &str,
+        messages: Vec<ChatMessage>,
+        participants: Vec<Participant>,
+    ) {
+        let room = match self.rooms.get_mut(id) {
+            Some(room) => room,
+            None => return,
+        };
+        for participant in participants {
+            let name = if participant.game_name.is_empty() {
+                participant.name
+            } else {
+                participant.game_name
+            };
+            room.names.insert(participant.summoner_id, name);
+        }
+        for message in messages {
+            room.push(message);
+        }
+        room.messages.sort_by_key(ChatMessage::time);
+        room.loaded = true;
+    }
+
+    /// The first room of `kind`, there's one lobby and one champ select at a time.
+    pub fn room(&mut self, kind: &str) -> Option<&mut Room> {
+        self.rooms
+            .values_mut()
+            .find(|room| room.conversation.kind == kind)
+    }
+
+    /// A system line of our own in the room of `kind`, for what the client doesn't announce.
+    pub fn note(&mut self, kind: &str, text: String) {
+        let id = format!("clowncher-{}", self.next_note);
+        self.next_note += 1;
+        if let Some(room) = self.room(kind) {
+            room.push(ChatMessage {
+                id,
+                body: text,
+                timestamp: Local::now().to_rfc3339(),
+                kind: "system".to_string(),
+                ..Default::default()
+            });
+        }
+    }
+
+    /// Remember names the lobby knows, for rooms whose participants we haven't loaded.
+    pub fn learn_names(&mut self, kind: &str, names: impl Iterator<Item = (i64, String)>) {
+        if let Some(room) = self.room(kind) {
+            room.names.extend(names);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn escaped_ids_are_single_segments() {
+        assert_eq!(escape("abc@sec.pvp.net"), "abc%40sec.pvp.net");
+        assert_eq!(escape("100%/a b"), "100%25%2Fa%20b");
+        assert_eq!(escape("plain-id_1.x~"), "plain-id_1.x~");
+    }
+
+    #[test]
+    fn ids_survive_the_round_trip() {
+        let ids = [
+            "abc@sec.pvp.net",
+            "lobby-0b9c@lol-lobby.pvp.net",
+            "100%@champ-select.pvp.net",
+            "a/b@sec.pvp.net",
+            "%40already@escaped",
+            "ünïcode@sec.pvp.net",
+            "",
+        ];
+        for id in ids {
+            assert_eq!(unescape(&escape(id)), id);
+            assert!(!escape(id).contains(['@', '/']));
+        }
+    }
+
+    #[test]
+    fn stray_percent_signs_are_kept() {
+        assert_eq!(unescape("100%"), "100%");
+        assert_eq!(unescape("%zz%4"), "%zz%4");
+    }
+
+    #[test]
+    fn conversation_uris_escape_the_id() {
+        assert_eq!(
+            conversation_uri("a/b@sec.pvp.net"),
+            format!("{}/a%2Fb%40sec.pvp.net", CONVERSATIONS)
+        );
+    }
+}
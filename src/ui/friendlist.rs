@@ -1,10 +1,18 @@
-use crate::widgets::dragdrop::drag_source;
+use crate::command::{Command, CommandQueue};
+use crate::logging;
+use crate::ui::chat::Chat;
+use crate::widgets::dragdrop::{drag_source, drop_target};
 use eframe::egui;
-use kassadin::client::LCU;
+use kassadin::types::lcu::chat::Friend;
 use kassadin::types::socket::FriendEvent;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::fmt::Debug;
 
+pub mod groups;
+
+use groups::{FriendGroup, FriendGroups, Membership};
+
 #[derive(Debug, Clone)]
 pub struct Rank {
     pub tier: Option<String>,
@@ -39,6 +47,11 @@ impl From<Option<String>> for Status {
 }
 
 impl Status {
+    /// What the group headers count as online.
+    pub fn is_online(&self) -> bool {
+        matches!(self, Status::Idle | Status::Away | Status::Ingame)
+    }
+
     pub fn to_color(&self) -> egui::Color32 {
         match *self {
             Status::Other => egui::Color32::BLACK,
@@ -71,11 +84,7 @@ impl From<kassadin::types::lcu::chat::Friend> for FriendListEntry {
             status: Status::from(f.availability),
             rank: Rank {
                 division: f.lol.ranked_league_division,
-                tier: if f.lol.ranked_league_tier.is_some() {
-                    Some(f.lol.ranked_league_tier.unwrap())
-                } else {
-                    None
-                },
+                tier: f.lol.ranked_league_tier,
             },
             id: f.id,
             summoner_id: f.summoner_id,
@@ -83,56 +92,74 @@ impl From<kassadin::types::lcu::chat::Friend> for FriendListEntry {
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub enum Sorting {
+    #[default]
     Status,
     NameAlphabet,
     NameReverseAlphabet,
     NameSearch(String),
 }
 
-impl Default for Sorting {
-    fn default() -> Self {
-        Self::Status
-    }
-}
-
 #[derive(Debug, Default, Clone, Serialize, Deserialize)]
 pub struct FriendlistConfig {
     pub sorting: Sorting,
 }
 
+/// A line of the friend list, all as high so only the ones in view are drawn.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Row {
+    /// the group at this index of `groups.groups`, with how many of its friends are online
+    Group {
+        group: usize,
+        online: usize,
+        total: usize,
+    },
+    /// rename and delete of the group at this index
+    Actions(usize),
+    /// the friend at this index of `friends`
+    Friend(usize),
+}
+
 #[derive(Debug, Default)]
 pub struct Friendlist {
     pub config: FriendlistConfig,
     pub friends: Vec<FriendListEntry>,
+    pub groups: FriendGroups,
+    /// `friends` by group, see `regroup`
+    rows: Vec<Row>,
     pub dragging_friend: Option<FriendListEntry>,
     pub hover_friend: Option<(FriendListEntry, egui::Pos2)>,
+    /// name typed in for a new group
+    new_group: String,
+    /// id and new name of the group being renamed
+    renaming: Option<(i64, String)>,
 }
 
 impl Friendlist {
-    pub fn new(lcu: &LCU) -> Friendlist {
-        let config = confy::load::<FriendlistConfig>("clowncher/friends").unwrap_or_default();
+    /// With the settings stored under the confy name `config`.
+    /// Friends themselves arrive through `Command::LoadFriends` once a client is connected.
+    pub fn load(config: &str) -> Friendlist {
+        let config = confy::load::<FriendlistConfig>(config).unwrap_or_default();
 
-        if lcu.is_client_running() {
-            let mut friendlist = Friendlist {
-                friends: Vec::new(),
-                config,
-                dragging_friend: None,
-                hover_friend: None,
-            };
-            friendlist.reload(lcu);
-            friendlist
-        } else {
-            Default::default()
+        Friendlist {
+            friends: Vec::new(),
+            groups: FriendGroups::default(),
+            rows: Vec::new(),
+            config,
+            dragging_friend: None,
+            hover_friend: None,
+            new_group: String::new(),
+            renaming: None,
         }
     }
 
-    pub fn save(&self) {
-        confy::store("clowncher/friends", self.config.clone()).unwrap();
+    pub fn save(&self, config: &str) -> crate::error::Result<()> {
+        confy::store(config, self.config.clone())?;
+        Ok(())
     }
 
-    pub fn ui(&mut self, ui: &mut egui::Ui) {
+    pub fn ui(&mut self, ui: &mut egui::Ui, chat: &mut Chat, commands: &mut CommandQueue) {
         if let Some(friend) = &self.hover_friend {
             let ctx = ui.ctx();
             egui::Window::new(&friend.0.name)
@@ -141,11 +168,12 @@ impl Friendlist {
                 ui.label("test");
             });
         }
-        self.ui_friends(ui);
+        self.ui_friends(ui, chat, commands);
     }
 
     pub fn update(&mut self, update: FriendEvent) {
         let friend = FriendListEntry::from(update);
+        log::trace!(target: logging::FRIENDS, "{} is {:?}", friend.name, friend.status);
         if let Some((id, _entry)) = self
             .friends
             .iter()
@@ -158,17 +186,24 @@ impl Friendlist {
         self.sort();
     }
 
-    pub fn reload(&mut self, lcu: &LCU) {
-        self.friends.clear();
-        self.friends = crate::RT
-            .block_on(async { lcu.chat().friends().await })
-            .unwrap()
-            .into_iter()
-            .map(|f| FriendListEntry::from(f))
-            .collect();
+    pub fn set_friends(&mut self, friends: Vec<Friend>) {
+        log::debug!(target: logging::FRIENDS, "loaded {} friends", friends.len());
+        self.friends = friends.into_iter().map(FriendListEntry::from).collect();
         self.sort();
     }
 
+    pub fn set_groups(&mut self, groups: Vec<FriendGroup>, members: Vec<Membership>) {
+        self.groups.set(groups, members);
+        self.regroup();
+    }
+
+    /// Forget the friends and groups of a client that's gone.
+    pub fn clear(&mut self) {
+        self.friends.clear();
+        self.groups.clear();
+        self.regroup();
+    }
+
     pub fn sort(&mut self) {
         match &self.config.sorting {
             Sorting::Status => {
@@ -179,35 +214,242 @@ impl Friendlist {
             Sorting::NameReverseAlphabet => unimplemented!(),
             Sorting::NameSearch(_name) => unimplemented!(),
         }
+        self.regroup();
     }
 
-    pub fn ui_friends(&mut self, ui: &mut egui::Ui) {
-        let text_style = egui::TextStyle::Body;
-        let row_height = ui.fonts()[text_style].row_height() + 30.0;
-        self.dragging_friend = None;
+    /// Bucket the friends into `rows` by group, again whenever the friends, the groups
+    /// or who is in which changes.
+    fn regroup(&mut self) {
+        self.rows.clear();
+        if !self.groups.is_loaded() {
+            self.rows.extend((0..self.friends.len()).map(Row::Friend));
+            return;
+        }
+        let index = self
+            .groups
+            .groups
+            .iter()
+            .enumerate()
+            .map(|(index, group)| (group.id, index))
+            .collect::<HashMap<_, _>>();
+        let mut buckets = vec![Vec::new(); self.groups.groups.len()];
+        for (id, friend) in self.friends.iter().enumerate() {
+            let group = self.groups.group_of(&friend.id);
+            if let Some(&group) = group.and_then(|group| index.get(&group)) {
+                buckets[group].push(id);
+            }
+        }
+        for (group, (entry, bucket)) in self.groups.groups.iter().zip(buckets).enumerate() {
+            let online = bucket
+                .iter()
+                .filter(|&&id| self.friends[id].status.is_online())
+                .count();
+            let total = bucket.len();
+            self.rows.push(Row::Group {
+                group,
+                online,
+                total,
+            });
+            if self.groups.is_collapsed(entry.id) {
+                continue;
+            }
+            if !entry.is_default() {
+                self.rows.push(Row::Actions(group));
+            }
+            self.rows.extend(bucket.into_iter().map(Row::Friend));
+        }
+    }
+
+    /// The friends by group, a flat list until the groups are loaded.
+    /// Only the rows in view are drawn.
+    pub fn ui_friends(&mut self, ui: &mut egui::Ui, chat: &mut Chat, commands: &mut CommandQueue) {
+        // the rows set it again, the drop targets go by what was dragged last frame
+        let dragging = self.dragging_friend.take();
         self.hover_friend = None;
+        if self.groups.is_loaded() {
+            self.ui_new_group(ui, commands);
+        }
+        // a friend's name and riot name with the space around them, the tallest row
+        let row_height = ui.fonts()[egui::TextStyle::Button].row_height()
+            + ui.fonts()[egui::TextStyle::Small].row_height()
+            + 3.0 * ui.spacing().item_spacing.y
+            + 20.0;
         egui::ScrollArea::vertical()
             .auto_shrink([false, true])
-            .show_rows(ui, row_height, self.friends.len(), |ui, row_range| {
-                for id in row_range {
-                    ui.add_space(10.0);
-                    let item_id = egui::Id::new("fl_drag").with(id);
-                    drag_source(ui, item_id, |ui| {
-                        let friend = self.ui_friend(ui, &self.friends[id]);
-                        if let Some(pos) = friend.hover_pos(){
-                            self.hover_friend = Some((self.friends[id].clone(), pos));
+            .show_rows(ui, row_height, self.rows.len(), |ui, row_range| {
+                for row in row_range {
+                    // a click or drop regroups, the rest waits for the next frame
+                    let row = match self.rows.get(row) {
+                        Some(&row) => row,
+                        None => break,
+                    };
+                    ui.vertical(|ui| {
+                        ui.set_min_height(row_height);
+                        match row {
+                            Row::Group {
+                                group,
+                                online,
+                                total,
+                            } => self.ui_group(
+                                ui,
+                                commands,
+                                group,
+                                (online, total),
+                                dragging.as_ref(),
+                            ),
+                            Row::Actions(group) => {
+                                let group = self.groups.groups[group].clone();
+                                self.ui_group_actions(ui, commands, &group);
+                            }
+                            Row::Friend(id) => self.ui_row(ui, chat, commands, id),
                         }
                     });
-                    ui.add_space(10.0);
+                }
+            });
+    }
 
-                    if ui.memory().is_being_dragged(item_id) {
-                        self.dragging_friend = Some(self.friends[id].clone());
+    /// The header of the group at `index` with its online count,
+    /// a click folds it and friends dragged onto it move there.
+    fn ui_group(
+        &mut self,
+        ui: &mut egui::Ui,
+        commands: &mut CommandQueue,
+        index: usize,
+        (online, total): (usize, usize),
+        dragging: Option<&FriendListEntry>,
+    ) {
+        let group = &self.groups.groups[index];
+        let id = group.id;
+        let collapsed = self.groups.is_collapsed(id);
+        let icon = if collapsed { "⏵" } else { "⏷" };
+        let title = format!("{} {} ({}/{})", icon, group.label(), online, total);
+        let can_accept =
+            dragging.is_some_and(|friend| self.groups.group_of(&friend.id) != Some(id));
+
+        let response = drop_target(ui, can_accept, |ui| ui.selectable_label(false, title));
+        if response.inner.clicked() {
+            self.groups.set_collapsed(id, !collapsed);
+            self.regroup();
+        }
+
+        if can_accept && response.response.hovered() && ui.input().pointer.any_released() {
+            let friend = dragging.unwrap();
+            let name = &self.groups.groups[index].name;
+            log::debug!(target: logging::FRIENDS, "moving {} to {}", friend.name, name);
+            self.groups.move_friend(&friend.id, id);
+            self.regroup();
+            commands.send(Command::MoveFriend(friend.id.clone(), id));
+        }
+    }
+
+    /// Rename and Delete, the default group has neither.
+    fn ui_group_actions(
+        &mut self,
+        ui: &mut egui::Ui,
+        commands: &mut CommandQueue,
+        group: &FriendGroup,
+    ) {
+        let pending = commands.is_pending(|c| match c {
+            Command::RenameFriendGroup(id, _) | Command::DeleteFriendGroup(id) => *id == group.id,
+            _ => false,
+        });
+        if matches!(&self.renaming, Some((id, _)) if *id == group.id) {
+            let groups = &self.groups;
+            let mut done = false;
+            if let Some((_, name)) = &mut self.renaming {
+                ui.horizontal(|ui| {
+                    ui.text_edit_singleline(name);
+                    let valid = groups.is_valid_name(name);
+                    if ui
+                        .add_enabled(valid && !pending, egui::Button::new("Save"))
+                        .clicked()
+                    {
+                        commands.send(Command::RenameFriendGroup(
+                            group.id,
+                            name.trim().to_string(),
+                        ));
+                        done = true;
+                    } else if ui.button("Cancel").clicked() {
+                        done = true;
                     }
+                });
+            }
+            if done {
+                self.renaming = None;
+            }
+            return;
+        }
+        ui.horizontal(|ui| {
+            if ui
+                .add_enabled(!pending, egui::Button::new("Rename").small())
+                .clicked()
+            {
+                self.renaming = Some((group.id, group.name.clone()));
+            }
+            if ui
+                .add_enabled(!pending, egui::Button::new("Delete").small())
+                .on_hover_text("Its friends move to General")
+                .clicked()
+            {
+                commands.send(Command::DeleteFriendGroup(group.id));
+            }
+        });
+    }
+
+    fn ui_new_group(&mut self, ui: &mut egui::Ui, commands: &mut CommandQueue) {
+        let pending = commands.is_pending(|c| matches!(c, Command::CreateFriendGroup(_)));
+        ui.horizontal(|ui| {
+            ui.add(egui::TextEdit::singleline(&mut self.new_group).hint_text("New group"));
+            let valid = self.groups.is_valid_name(&self.new_group);
+            if ui
+                .add_enabled(valid && !pending, egui::Button::new("Create"))
+                .clicked()
+            {
+                let name = std::mem::take(&mut self.new_group);
+                commands.send(Command::CreateFriendGroup(name.trim().to_string()));
+            }
+        });
+    }
+
+    /// The friend at `id` in `friends`, they can be dragged into the lobby or another group.
+    fn ui_row(
+        &mut self,
+        ui: &mut egui::Ui,
+        chat: &mut Chat,
+        commands: &mut CommandQueue,
+        id: usize,
+    ) {
+        ui.add_space(10.0);
+        let item_id = egui::Id::new("fl_drag").with(&self.friends[id].id);
+        let unread = chat.unread(&self.friends[id].id);
+        ui.horizontal(|ui| {
+            drag_source(ui, item_id, |ui| {
+                let friend = self.ui_friend(ui, &self.friends[id], unread);
+                if let Some(pos) = friend.hover_pos() {
+                    self.hover_friend = Some((self.friends[id].clone(), pos));
                 }
             });
+            if ui.small_button("Chat").clicked() {
+                let friend = &self.friends[id];
+                if chat.open_direct(&friend.id, &friend.name) {
+                    commands.send(Command::OpenConversation(friend.id.clone()));
+                }
+            }
+        });
+        ui.add_space(10.0);
+
+        if ui.memory().is_being_dragged(item_id) {
+            self.dragging_friend = Some(self.friends[id].clone());
+        }
     }
 
-    pub fn ui_friend(&self, ui: &mut egui::Ui, f: &FriendListEntry) -> egui::Response {
+    /// `unread` direct messages show as a badge next to the name.
+    pub fn ui_friend(
+        &self,
+        ui: &mut egui::Ui,
+        f: &FriendListEntry,
+        unread: usize,
+    ) -> egui::Response {
         let name =
             egui::Label::new(egui::RichText::new(&f.name).text_style(egui::TextStyle::Button));
 
@@ -220,10 +462,135 @@ impl Friendlist {
             let rect = response.rect;
             painter.circle_filled(rect.center(), rect.width() / 2.0, f.status.to_color());
             ui.vertical(|ui| {
-                ui.add(name);
+                ui.horizontal(|ui| {
+                    ui.add(name);
+                    if unread > 0 {
+                        ui.label(
+                            egui::RichText::new(format!(" {} ", unread))
+                                .text_style(egui::TextStyle::Small)
+                                .color(egui::Color32::WHITE)
+                                .background_color(crate::ui::colors::RED_A500),
+                        );
+                    }
+                });
                 ui.add(riot_name);
             });
         })
         .response
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn friend(id: &str, status: Status) -> FriendListEntry {
+        FriendListEntry {
+            name: id.to_string(),
+            riot_name: id.to_string(),
+            icon: 0,
+            status,
+            rank: Rank {
+                tier: None,
+                division: None,
+            },
+            id: id.to_string(),
+            summoner_id: 0,
+        }
+    }
+
+    fn group(id: i64, name: &str, collapsed: bool) -> FriendGroup {
+        FriendGroup {
+            id,
+            name: name.to_string(),
+            priority: id,
+            collapsed,
+            ..Default::default()
+        }
+    }
+
+    fn member(id: &str, group_id: i64) -> Membership {
+        Membership {
+            id: id.to_string(),
+            group_id,
+        }
+    }
+
+    /// Friends sorted by status: `a` is online, `b` away, `c` offline.
+    fn friendlist() -> Friendlist {
+        let mut friendlist = Friendlist {
+            friends: vec![
+                friend("a", Status::Idle),
+                friend("b", Status::Away),
+                friend("c", Status::Offline),
+            ],
+            ..Default::default()
+        };
+        friendlist.sort();
+        friendlist
+    }
+
+    #[test]
+    fn flat_until_the_groups_load() {
+        let friendlist = friendlist();
+        assert_eq!(
+            friendlist.rows,
+            [Row::Friend(0), Row::Friend(1), Row::Friend(2)]
+        );
+    }
+
+    #[test]
+    fn friends_are_bucketed_by_group() {
+        let mut friendlist = friendlist();
+        let groups = vec![group(2, "duo", false), group(1, "**Default", false)];
+        // `c` was added since and lands in the default group
+        friendlist.set_groups(groups, vec![member("a", 2), member("b", 1)]);
+        assert_eq!(
+            friendlist.rows,
+            [
+                Row::Group {
+                    group: 0,
+                    online: 1,
+                    total: 2
+                },
+                Row::Friend(1),
+                Row::Friend(2),
+                Row::Group {
+                    group: 1,
+                    online: 1,
+                    total: 1
+                },
+                Row::Actions(1),
+                Row::Friend(0),
+            ]
+        );
+
+        friendlist.groups.move_friend("b", 2);
+        friendlist.regroup();
+        assert!(matches!(
+            friendlist.rows[..2],
+            [Row::Group { total: 1, .. }, Row::Friend(2)]
+        ));
+    }
+
+    #[test]
+    fn folded_groups_keep_their_header() {
+        let mut friendlist = friendlist();
+        let groups = vec![group(1, "**Default", false), group(2, "duo", true)];
+        friendlist.set_groups(groups.clone(), vec![member("a", 2)]);
+        assert_eq!(friendlist.rows.len(), 4);
+        assert_eq!(
+            friendlist.rows[3],
+            Row::Group {
+                group: 1,
+                online: 1,
+                total: 1
+            }
+        );
+
+        // unfolding here outlasts a reload
+        friendlist.groups.set_collapsed(2, false);
+        friendlist.set_groups(groups, vec![member("a", 2)]);
+        assert_eq!(friendlist.rows[4..], [Row::Actions(1), Row::Friend(0)]);
+    }
+}
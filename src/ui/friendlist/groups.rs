@@ -0,0 +1,130 @@
+//! The client's friend groups, from `/lol-chat/v1/friend-groups`.
+//! Which group a friend is in comes from `/lol-chat/v1/friends`, kassadin's `Friend` leaves it out,
+//! so it's only as fresh as the last `Command::LoadFriendGroups`.
+
+use serde::Deserialize;
+use std::collections::{HashMap, HashSet};
+
+pub const FRIEND_GROUPS: &str = "/lol-chat/v1/friend-groups";
+
+/// the group new friends land in, it can't be renamed or deleted
+const DEFAULT: &str = "**Default";
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct FriendGroup {
+    pub id: i64,
+    pub name: String,
+    /// the client's order, ascending
+    pub priority: i64,
+    /// the client's own sections like offline friends, not a group one can be in
+    pub is_meta_group: bool,
+    pub collapsed: bool,
+}
+
+impl FriendGroup {
+    pub fn is_default(&self) -> bool {
+        self.name == DEFAULT
+    }
+
+    /// The name the friend list shows, the default group's is a placeholder.
+    pub fn label(&self) -> &str {
+        if self.is_default() {
+            "General"
+        } else {
+            &self.name
+        }
+    }
+}
+
+/// A friend as `/lol-chat/v1/friends` lists them, only what says where they belong.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct Membership {
+    /// the friend's chat id
+    pub id: String,
+    pub group_id: i64,
+}
+
+#[derive(Debug, Default)]
+pub struct FriendGroups {
+    /// empty until loaded
+    pub groups: Vec<FriendGroup>,
+    /// group id by friend chat id
+    members: HashMap<String, i64>,
+    /// ids of the folded groups, as the client has them until folded here
+    collapsed: HashSet<i64>,
+}
+
+impl FriendGroups {
+    pub fn set(&mut self, mut groups: Vec<FriendGroup>, members: Vec<Membership>) {
+        groups.retain(|group| !group.is_meta_group);
+        groups.sort_by_key(|group| group.priority);
+        let known = self
+            .groups
+            .iter()
+            .map(|group| group.id)
+            .collect::<HashSet<_>>();
+        self.collapsed
+            .retain(|id| groups.iter().any(|group| group.id == *id));
+        self.collapsed.extend(
+            groups
+                .iter()
+                .filter(|group| group.collapsed && !known.contains(&group.id))
+                .map(|group| group.id),
+        );
+        self.groups = groups;
+        self.members = members
+            .into_iter()
+            .map(|member| (member.id, member.group_id))
+            .collect();
+    }
+
+    pub fn clear(&mut self) {
+        self.groups.clear();
+        self.members.clear();
+        self.collapsed.clear();
+    }
+
+    pub fn is_loaded(&self) -> bool {
+        !self.groups.is_empty()
+    }
+
+    /// The group of the friend `id`, friends added since the last load are in the default one.
+    pub fn group_of(&self, id: &str) -> Option<i64> {
+        self.members.get(id).copied().or_else(|| {
+            self.groups
+                .iter()
+                .find(|group| group.is_default())
+                .map(|group| group.id)
+        })
+    }
+
+    pub fn is_collapsed(&self, id: i64) -> bool {
+        self.collapsed.contains(&id)
+    }
+
+    pub fn set_collapsed(&mut self, id: i64, collapsed: bool) {
+        if collapsed {
+            self.collapsed.insert(id);
+        } else {
+            self.collapsed.remove(&id);
+        }
+    }
+
+    /// Move right away instead of waiting for the client, a failed move reloads.
+    pub fn move_friend(&mut self, id: &str, group_id: i64) {
+        self.members.insert(id.to_string(), group_id);
+    }
+
+    /// Whether `name` can be given to a group, the client refuses duplicates.
+    pub fn is_valid_name(&self, name: &str) -> bool {
+        let name = name.trim();
+        !name.is_empty()
+            && name != DEFAULT
+            && !self
+                .groups
+                .iter()
+                .any(|group| group.name.eq_ignore_ascii_case(name))
+    }
+}
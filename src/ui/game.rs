@@ -1,49 +1,42 @@
 use eframe::egui;
 
-use kassadin::client::LCU;
-use kassadin::types::lcu::consts::{Position, QueueId, Tier};
+use kassadin::types::lcu::consts::{Position, QueueId};
 use kassadin::types::lcu::lobby::{Member, PositionPreference};
 use serde::{Deserialize, Serialize};
 
-use kassadin::types::consts::Division;
-use kassadin::types::lcu::ranked::RankedStatus;
+use kassadin::types::socket::GameFlowPhase;
 use std::string::ToString;
+use std::time::{Duration, Instant};
 
-use crate::widgets::dragdrop::drop_target;
+use chrono::{DateTime, Local};
+
+use crate::command::{Command, CommandQueue};
+use crate::logging;
+use crate::queues::{self, GameQueue, QueueList};
+use crate::repaint;
+use crate::ui::account::Account;
+use crate::ui::chat::{self, Chat};
 use crate::ui::friendlist::Friendlist;
+use crate::widgets::dragdrop::drop_target;
 use crate::TextureManager;
 
-#[derive(Debug, PartialEq)]
-pub enum SearchState {
-    None,
-    Lobby,
-    Searching,
-    Found,
-    ChampSelect,
-    InGame,
-    AfterGameLobby,
-    Error,
-}
-
-impl Default for SearchState {
-    fn default() -> Self {
-        Self::None
-    }
-}
+pub mod champ_select;
+pub mod flow;
+pub mod invitations;
+pub mod lobby;
+pub mod ranked;
 
-#[derive(Debug, Clone)]
-pub struct LobbyMemberRanked {
-    pub division: Division,
-    pub tier: Tier,
-    pub wins: i64,
-    pub losses: i64,
-    pub lp: i64,
-}
+use champ_select::ChampSelect;
+pub use flow::SearchState;
+use flow::{GameFlow, Transition};
+use invitations::{Inbox, ReceivedInvitation};
+use lobby::LobbyInvitation;
+use ranked::{Lookup, RankedCache, RankedStats};
 
 #[derive(Debug, Clone)]
 pub struct LobbyMember {
     pub name: String,
-    pub ranked: LobbyMemberRanked,
+    pub puuid: Option<String>,
     pub autofillable: bool,
     pub leader: bool,
     pub positions: PositionPreference,
@@ -51,21 +44,14 @@ pub struct LobbyMember {
 }
 
 impl LobbyMember {
-    pub fn from(m: Member, ranked: RankedStatus) -> Self {
-        let ranked = LobbyMemberRanked {
-            division: ranked.queue_map.ranked_solo.division,
-            tier: ranked.queue_map.ranked_solo.tier,
-            wins: ranked.queue_map.ranked_solo.wins,
-            losses: ranked.queue_map.ranked_solo.losses,
-            lp: ranked.queue_map.ranked_solo.league_points,
-        };
+    pub fn from(m: Member) -> Self {
         let positions = PositionPreference {
             first_preference: m.first_position_preference,
             second_preference: m.second_position_preference,
         };
         Self {
             name: m.summoner_name.unwrap_or_default(),
-            ranked,
+            puuid: m.puuid,
             autofillable: m.auto_fill_eligible.unwrap_or_default(),
             leader: m.is_leader.unwrap_or_default(),
             positions,
@@ -75,71 +61,270 @@ impl LobbyMember {
 }
 
 #[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct GameConfig {
     pub auto_accept: bool,
+    /// seconds between the ready check popping and accepting it, time to cancel
+    pub auto_accept_delay: u64,
+    /// only accept matches of these queues, all queues if empty
+    pub auto_accept_queues: Vec<QueueId>,
+    pub auto_accept_while_away: bool,
+}
+
+/// A match that was accepted without anyone clicking.
+#[derive(Debug, Clone)]
+pub struct AutoAccepted {
+    pub time: DateTime<Local>,
+    pub queue_id: Option<i64>,
 }
 
 #[derive(Debug)]
 pub struct Game {
     pub config: GameConfig,
-    pub queue_id: Option<QueueId>,
-    pub search_sate: SearchState,
+    /// the lobby's queue as the client numbers it, `None` outside of lobbies and in customs
+    pub queue_id: Option<i64>,
+    /// the queue of the last lobby, Play Again rejoins it
+    pub last_queue_id: Option<i64>,
+    /// what the game select offers, kept across reconnects until reloaded
+    pub queues: QueueList,
+    pub flow: GameFlow,
     pub queue_timer: Option<f64>,
+    /// when `queue_timer` was last reported, it keeps counting in between
+    pub queue_timer_updated: Option<Instant>,
     pub estimated_queue_time: Option<f64>,
     pub positions: PositionPreference,
     pub members: Vec<LobbyMember>,
+    /// invitations sent from the lobby, answered ones included
+    pub invitations: Vec<LobbyInvitation>,
+    pub ranked: RankedCache,
+    /// invitations others sent us
+    pub inbox: Inbox,
+    /// `Some` while in champ select
+    pub champ_select: Option<ChampSelect>,
+    /// when the current ready check gets accepted, `None` if it won't be
+    pub auto_accept_at: Option<Instant>,
+    pub auto_accepted: Vec<AutoAccepted>,
     pub select_second: bool,
 }
 
-impl Default for Game {
-    fn default() -> Self {
-        let config = confy::load::<GameConfig>("clowncher/game").unwrap_or_default();
+impl Game {
+    /// With the settings stored under the confy name `config`.
+    pub fn load(config: &str) -> Self {
+        Self::new(confy::load::<GameConfig>(config).unwrap_or_default())
+    }
 
+    pub fn new(config: GameConfig) -> Self {
         Self {
             queue_id: None,
-            search_sate: SearchState::None,
+            last_queue_id: None,
+            queues: Default::default(),
+            flow: Default::default(),
             queue_timer: None,
+            queue_timer_updated: None,
             estimated_queue_time: None,
             positions: Default::default(),
             members: vec![],
+            invitations: vec![],
+            ranked: Default::default(),
+            inbox: Default::default(),
+            champ_select: None,
+            auto_accept_at: None,
+            auto_accepted: vec![],
             select_second: false,
             config,
         }
     }
-}
 
-impl Game {
-    pub fn save(&self) {
-        confy::store("clowncher/game", self.config.clone()).unwrap();
+    pub fn save(&self, config: &str) -> crate::error::Result<()> {
+        confy::store(config, self.config.clone())?;
+        Ok(())
     }
 
     pub fn ui(
         &mut self,
         ui: &mut egui::Ui,
-        lcu: &LCU,
+        commands: &mut CommandQueue,
         textures: &TextureManager,
         friendlist: &Friendlist,
+        chat: &mut Chat,
     ) {
         ui.vertical(|ui| {
-            self.ui_lobby(ui, lcu, textures, friendlist);
+            self.ui_lobby(ui, commands, textures, friendlist, chat);
             ui.with_layout(egui::Layout::left_to_right(), |ui| {
-                self.ui_selection(ui, lcu, textures);
+                self.ui_selection(ui, commands, textures);
+            });
+            let invitations = format!("Invitations ({})", self.inbox.pending().len());
+            egui::CollapsingHeader::new(invitations)
+                .id_source("invitations")
+                .show(ui, |ui| {
+                    self.ui_inbox(ui, commands);
+                });
+            ui.collapsing("Auto accept", |ui| {
+                self.ui_auto_accept(ui);
+            });
+            ui.collapsing("Timeline", |ui| {
+                self.ui_timeline(ui);
             });
         });
+
+        // in a lobby or game the inbox is enough, there's no need to interrupt
+        if self.flow.state() == SearchState::None {
+            self.ui_invitation_notification(ui.ctx(), commands);
+        }
+    }
+
+    /// Forget everything learned from the socket, e.g. after the client restarted.
+    pub fn reset(&mut self) {
+        self.queue_id = None;
+        self.flow.reset();
+        self.queue_timer = None;
+        self.queue_timer_updated = None;
+        self.estimated_queue_time = None;
+        self.members.clear();
+        self.invitations.clear();
+        self.inbox.clear();
+        self.champ_select = None;
+        self.auto_accept_at = None;
+    }
+
+    /// Time spent searching so far, counted on from the client's last report.
+    pub fn time_in_queue(&self, now: Instant) -> Option<Duration> {
+        let reported = Duration::from_secs_f64(self.queue_timer?.max(0.0));
+        Some(reported + now.saturating_duration_since(self.queue_timer_updated?))
+    }
+
+    /// When one of the shown clocks changes next, `None` if none is running.
+    pub fn next_repaint(&self, now: Instant) -> Option<Instant> {
+        let mut next = None;
+        if self.flow.state() == SearchState::Searching {
+            if let Some(in_queue) = self.time_in_queue(now) {
+                // counting up, the next second is a full one minus what passed of this one
+                let passed = Duration::from_nanos(in_queue.subsec_nanos() as u64);
+                next = Some(now + (Duration::from_secs(1) - passed));
+            }
+        }
+        if let Some(at) = self.auto_accept_at {
+            let left = at.saturating_duration_since(now);
+            next = repaint::earliest(next, repaint::next_second(now, left));
+        }
+        if let Some(left) = self.champ_select.as_ref().and_then(|c| c.time_left(now)) {
+            next = repaint::earliest(next, repaint::next_second(now, left));
+        }
+        next
+    }
+
+    /// The client entered `phase`.
+    pub fn gameflow(&mut self, phase: &GameFlowPhase, account: &Account) {
+        let from = self.flow.state();
+        match self.flow.advance(phase, Local::now()) {
+            Transition::Unchanged => return,
+            Transition::Entered => {
+                log::debug!(target: logging::LOBBY, "gameflow phase {:?}", phase);
+            }
+            Transition::Unexpected { from } => {
+                log::warn!(
+                    target: logging::LOBBY,
+                    "gameflow phase {:?} after {:?}, missed an update?",
+                    phase,
+                    from
+                );
+            }
+        }
+        if self.flow.state() == SearchState::Found && from != SearchState::Found {
+            self.ready_check(account);
+        }
     }
 
-    pub fn update_members(&mut self, members: Vec<LobbyMember>) {
+    /// A ready check popped, schedule accepting it if the rules allow.
+    pub fn ready_check(&mut self, account: &Account) {
+        let config = &self.config;
+        let queue_allowed = config.auto_accept_queues.is_empty()
+            || self
+                .queue_id
+                .and_then(queues::known)
+                .is_some_and(|queue_id| config.auto_accept_queues.contains(&queue_id));
+        let present = config.auto_accept_while_away || !account.is_away();
+
+        self.auto_accept_at = if config.auto_accept && queue_allowed && present {
+            Some(Instant::now() + Duration::from_secs(config.auto_accept_delay))
+        } else {
+            None
+        };
+    }
+
+    /// Accept the ready check once its delay ran out, called every frame.
+    pub fn auto_accept(&mut self, commands: &mut CommandQueue) {
+        let at = match self.auto_accept_at {
+            Some(at) => at,
+            None => return,
+        };
+        if self.flow.state() != SearchState::Found {
+            self.auto_accept_at = None;
+            return;
+        }
+        if Instant::now() < at {
+            return;
+        }
+
+        self.auto_accept_at = None;
+        commands.send(Command::Accept);
+        let accepted = AutoAccepted {
+            time: Local::now(),
+            queue_id: self.queue_id,
+        };
+        log::info!(target: logging::LOBBY, "auto accepted: {:?}", accepted);
+        self.auto_accepted.push(accepted);
+    }
+
+    /// Take the new members and look up ranked stats for the ones we don't know yet.
+    /// Which queues we may join depends on the party, so they are reloaded when it changes size.
+    pub fn update_members(&mut self, members: Vec<LobbyMember>, commands: &mut CommandQueue) {
+        if members.len() != self.members.len() {
+            commands.send(Command::LoadQueues);
+        }
+        let now = Instant::now();
+        for puuid in members.iter().filter_map(|member| member.puuid.as_deref()) {
+            if self.ranked.start_lookup(puuid, now) {
+                commands.send(Command::RankedStats(puuid.to_string()));
+            }
+        }
         self.members = members;
     }
 
+    /// Invite `summoner_id` unless they are in the lobby, invited or being invited already,
+    /// so dropping the same friend twice sends one invitation.
+    pub fn invite(&mut self, commands: &mut CommandQueue, summoner_id: i64, name: &str) {
+        let member = self
+            .members
+            .iter()
+            .any(|member| member.summoner_id == summoner_id);
+        let invited = self
+            .invitations
+            .iter()
+            .any(|invitation| invitation.to_summoner_id == summoner_id && invitation.is_open());
+        let inviting =
+            commands.is_pending(|c| matches!(c, Command::Invite(id) if *id == summoner_id));
+        if member || invited || inviting {
+            log::debug!(target: logging::LOBBY, "{} is already invited", name);
+            return;
+        }
+        log::info!(target: logging::LOBBY, "inviting {}", name);
+        commands.send(Command::Invite(summoner_id));
+    }
+
+    pub fn update_ranked(&mut self, puuid: &str, ranked: RankedStats) {
+        self.ranked.insert(puuid, ranked, Instant::now());
+    }
+
     fn ui_lobby(
         &mut self,
         ui: &mut egui::Ui,
-        lcu: &LCU,
+        commands: &mut CommandQueue,
         textures: &TextureManager,
         friendlist: &Friendlist,
+        chat: &mut Chat,
     ) {
-        match self.search_sate {
+        match self.flow.state() {
             SearchState::None => {
                 ui.label(
                     egui::RichText::new("Not in lobby")
@@ -148,52 +333,68 @@ impl Game {
                 );
             }
             SearchState::Lobby => {
-                let response = drop_target(ui, friendlist.dragging_friend.is_some(), |ui| {
-                    self.ui_members(ui, lcu, textures);
-                })
-                .response;
+                ui.horizontal(|ui| {
+                    ui.vertical(|ui| {
+                        let response =
+                            drop_target(ui, friendlist.dragging_friend.is_some(), |ui| {
+                                self.ui_members(ui, commands, textures);
+                            })
+                            .response;
 
-                if ui.memory().is_anything_being_dragged()
-                    && friendlist.dragging_friend.is_some()
-                    && response.hovered()
-                    && ui.input().pointer.any_released()
-                {
-                    let friend = friendlist.dragging_friend.as_ref().unwrap();
-                    println!("friend: {:?}", friend);
-                    match crate::RT
-                        .block_on(async { lcu.lobby().invite_member(friend.summoner_id).await })
-                    {
-                        Ok(_response) => {}
-                        Err(_e) => {}
-                    }
-                }
+                        let dropped = ui.memory().is_anything_being_dragged()
+                            && response.hovered()
+                            && ui.input().pointer.any_released();
+                        if let Some(friend) =
+                            friendlist.dragging_friend.as_ref().filter(|_| dropped)
+                        {
+                            self.invite(commands, friend.summoner_id, &friend.name);
+                        }
+                        self.ui_invitations(ui, commands);
+                    });
+                    ui_chat(ui, commands, chat, chat::LOBBY);
+                });
             }
             SearchState::Searching => {
-                self.ui_members(ui, lcu, textures);
+                ui.horizontal(|ui| {
+                    ui.vertical(|ui| {
+                        self.ui_members(ui, commands, textures);
+                    });
+                    ui_chat(ui, commands, chat, chat::LOBBY);
+                });
             }
             SearchState::Found => {}
-            SearchState::ChampSelect => {}
+            SearchState::ChampSelect => {
+                ui_chat(ui, commands, chat, chat::CHAMP_SELECT);
+            }
             SearchState::InGame => {}
-            SearchState::AfterGameLobby => {
-                self.ui_after_game_lobby(ui, lcu);
+            SearchState::AfterGameLobby | SearchState::EndOfGame => {
+                self.ui_after_game_lobby(ui, commands);
             }
             SearchState::Error => {}
         }
     }
 
-    fn ui_members(&mut self, ui: &mut egui::Ui, lcu: &LCU, textures: &TextureManager) {
+    fn ui_members(
+        &mut self,
+        ui: &mut egui::Ui,
+        commands: &mut CommandQueue,
+        textures: &TextureManager,
+    ) {
         for member in &self.members {
             ui.add_space(5.0);
             ui.horizontal(|ui| {
+                let kicking = commands
+                    .is_pending(|c| matches!(c, Command::Kick(id) if *id == member.summoner_id));
                 if ui
-                    .add(egui::Button::new(
-                        egui::RichText::new("X").color(crate::ui::colors::RED_A500),
-                    ))
+                    .add_enabled(
+                        !kicking,
+                        egui::Button::new(
+                            egui::RichText::new("X").color(crate::ui::colors::RED_A500),
+                        ),
+                    )
                     .clicked()
                 {
-                    crate::RT
-                        .block_on(async { lcu.lobby().kick_member(member.summoner_id).await })
-                        .unwrap();
+                    commands.send(Command::Kick(member.summoner_id));
                 }
                 ui.vertical(|ui| {
                     ui.horizontal(|ui| {
@@ -230,59 +431,123 @@ impl Game {
                         }
                     });
                     ui.add_space(2.0);
-                    // TODO: add riot api to display losses because lcu can't do that ?XD
-                    let ranked_label = format!(
-                        "{} {} | {} LP | {} wins",
-                        member.ranked.tier.to_string(),
-                        member.ranked.division.to_string(),
-                        member.ranked.lp.to_string(),
-                        member.ranked.wins.to_string(),
-                    );
-                    ui.label(egui::RichText::new(&ranked_label));
+                    let lookup = match &member.puuid {
+                        Some(puuid) => self.ranked.lookup(puuid),
+                        None => Lookup::Failed,
+                    };
+                    ui_ranked(ui, lookup, RankedStats::main_queue(self.queue_id));
                     ui.add(egui::Separator::default());
                 });
             });
         }
     }
 
-    fn ui_selection(&mut self, ui: &mut egui::Ui, lcu: &LCU, textures: &TextureManager) {
-        match self.search_sate {
+    /// Who was invited and what came of it.
+    fn ui_invitations(&mut self, ui: &mut egui::Ui, commands: &mut CommandQueue) {
+        if self.invitations.is_empty() {
+            return;
+        }
+        let shown = self.invitations.clone();
+
+        ui.add_space(5.0);
+        ui.label(egui::RichText::new("Invited").text_style(egui::TextStyle::Small));
+        for invitation in shown {
+            ui.horizontal(|ui| {
+                let (state, color) = match invitation.state.as_str() {
+                    "Requested" | "Pending" => ("pending", crate::ui::colors::GRAY_A500),
+                    "Accepted" => ("accepted", crate::ui::colors::GREEN_A400),
+                    "Declined" => ("declined", crate::ui::colors::YELLOW_A800),
+                    "Kicked" => ("cancelled", crate::ui::colors::GRAY_A500),
+                    _ => ("error", crate::ui::colors::RED_A500),
+                };
+                ui.label(&invitation.to_summoner_name);
+                ui.label(egui::RichText::new(state).color(color));
+
+                let summoner_id = invitation.to_summoner_id;
+                let joined = self
+                    .members
+                    .iter()
+                    .any(|member| member.summoner_id == summoner_id);
+                if joined {
+                    // in the lobby now, kicking them is up to the member list
+                } else if invitation.is_open() {
+                    // the client revokes invitations by kicking the invitee
+                    let cancelling = commands
+                        .is_pending(|c| matches!(c, Command::Kick(id) if *id == summoner_id));
+                    if ui
+                        .add_enabled(
+                            !cancelling,
+                            egui::Button::new(pending_text("Cancel", cancelling)),
+                        )
+                        .clicked()
+                    {
+                        commands.send(Command::Kick(summoner_id));
+                    }
+                } else {
+                    let inviting = commands
+                        .is_pending(|c| matches!(c, Command::Invite(id) if *id == summoner_id));
+                    if ui
+                        .add_enabled(
+                            !inviting,
+                            egui::Button::new(pending_text("Re-invite", inviting)),
+                        )
+                        .clicked()
+                    {
+                        self.invite(commands, summoner_id, &invitation.to_summoner_name);
+                    }
+                }
+            });
+        }
+    }
+
+    fn ui_selection(
+        &mut self,
+        ui: &mut egui::Ui,
+        commands: &mut CommandQueue,
+        textures: &TextureManager,
+    ) {
+        match self.flow.state() {
             SearchState::None => {
-                self.ui_selection_none(ui, lcu, textures);
+                self.ui_selection_none(ui, commands, textures);
             }
             SearchState::Lobby => {
-                self.ui_selection_lobby(ui, lcu, textures);
+                self.ui_selection_lobby(ui, commands, textures);
             }
             SearchState::Searching => {
-                self.ui_selection_searching(ui, lcu);
+                self.ui_selection_searching(ui, commands);
             }
             SearchState::Found => {
-                self.ui_selection_found(ui, lcu);
+                self.ui_selection_found(ui, commands);
             }
             SearchState::ChampSelect => {
-                self.ui_selection_champ_select(ui, lcu);
+                self.ui_selection_champ_select(ui, commands);
             }
             SearchState::InGame => {
                 self.ui_selection_in_game();
             }
-            SearchState::AfterGameLobby => {
-                self.ui_selection_lobby(ui, lcu, textures);
+            SearchState::AfterGameLobby | SearchState::EndOfGame => {
+                self.ui_selection_lobby(ui, commands, textures);
             }
             SearchState::Error => {
-                self.ui_selection_error(ui, lcu);
+                self.ui_selection_error(ui, commands);
             }
         }
     }
 
+    /// `unavailable` greys the button out and says why on hover.
     pub fn ui_game_button(
         &mut self,
         ui: &mut egui::Ui,
-        lcu: &LCU,
-        t: QueueId,
+        commands: &mut CommandQueue,
+        t: i64,
         text: impl Into<String>,
+        unavailable: Option<String>,
     ) {
+        let pending =
+            commands.is_pending(|c| matches!(c, Command::JoinLobby(..) | Command::LeaveLobby));
         if self.queue_id.is_some() && self.queue_id.unwrap() == t {
-            let button = ui.add(
+            let button = ui.add_enabled(
+                !pending,
                 egui::Button::new(
                     egui::RichText::new(text)
                         .text_style(egui::TextStyle::Button)
@@ -292,39 +557,39 @@ impl Game {
             );
             if button.clicked() {
                 self.queue_id = None;
-                crate::RT
-                    .block_on(async { lcu.lobby().leave_lobby().await })
-                    .unwrap();
+                commands.send(Command::LeaveLobby);
             }
         } else {
-            let button = ui.add(
+            let mut button = ui.add_enabled(
+                !pending && unavailable.is_none(),
                 egui::Button::new(
                     egui::RichText::new(text)
                         .text_style(egui::TextStyle::Button)
                         .color(egui::Color32::WHITE),
                 )
-                .fill(crate::ui::colors::BLUE_A400),
+                .fill(match unavailable {
+                    Some(_) => crate::ui::colors::GRAY_A500,
+                    None => crate::ui::colors::BLUE_A400,
+                }),
             );
+            if let Some(reason) = unavailable {
+                button = button.on_disabled_hover_text(reason);
+            }
             if button.clicked() {
                 self.queue_id = Some(t);
-                self.join_lobby(lcu);
+                self.join_lobby(commands);
             }
         }
     }
 
-    pub fn join_lobby(&mut self, lcu: &LCU) {
-        match crate::RT.block_on(async {
-            lcu.lobby()
-                .join_lobby(self.queue_id.unwrap())
-                .await
-                .and(lcu.lobby().set_roles(&self.positions).await)
-        }) {
-            Ok(_response) => {}
-            Err(_e) => {}
-        }
+    pub fn join_lobby(&mut self, commands: &mut CommandQueue) {
+        commands.send(Command::JoinLobby(
+            self.queue_id.unwrap(),
+            self.positions.clone(),
+        ));
     }
 
-    pub fn update_position(&mut self, lcu: &LCU, position: Position) {
+    pub fn update_position(&mut self, commands: &mut CommandQueue, position: Position) {
         if self.positions.first_preference.is_some()
             && self.positions.first_preference.unwrap() == position
         {
@@ -343,16 +608,18 @@ impl Game {
             self.select_second = false;
         }
 
-        if self.search_sate == SearchState::Lobby {
-            match crate::RT.block_on(async { lcu.lobby().set_roles(&self.positions).await }) {
-                Ok(_response) => {}
-                Err(_e) => {}
-            }
+        if self.flow.state() == SearchState::Lobby {
+            commands.send(Command::SetRoles(self.positions.clone()));
         }
     }
 
-    fn ui_selection_none(&mut self, ui: &mut egui::Ui, lcu: &LCU, textures: &TextureManager) {
-        self.join_lobby_buttons(ui, lcu);
+    fn ui_selection_none(
+        &mut self,
+        ui: &mut egui::Ui,
+        commands: &mut CommandQueue,
+        textures: &TextureManager,
+    ) {
+        self.join_lobby_buttons(ui, commands);
 
         ui.add(
             egui::Button::new(
@@ -368,34 +635,41 @@ impl Game {
             }),
         );
 
-        self.ui_role_buttons(ui, lcu, textures);
+        self.ui_role_buttons(ui, commands, textures);
     }
 
-    fn ui_selection_lobby(&mut self, ui: &mut egui::Ui, lcu: &LCU, textures: &TextureManager) {
-        self.join_lobby_buttons(ui, lcu);
+    fn ui_selection_lobby(
+        &mut self,
+        ui: &mut egui::Ui,
+        commands: &mut CommandQueue,
+        textures: &TextureManager,
+    ) {
+        self.join_lobby_buttons(ui, commands);
 
-        let button = ui.add(
+        let pending = commands.is_pending(|c| matches!(c, Command::StartQueue));
+        let button = ui.add_enabled(
+            !pending,
             egui::Button::new(
-                egui::RichText::new("Search")
+                egui::RichText::new(pending_text("Search", pending))
                     .text_style(egui::TextStyle::Heading)
                     .color(crate::ui::colors::YELLOW_A800),
             )
             .fill(crate::ui::colors::GREEN_A400),
         );
 
-        self.ui_role_buttons(ui, lcu, textures);
+        self.ui_role_buttons(ui, commands, textures);
 
         if button.clicked() {
-            crate::RT
-                .block_on(async { lcu.lobby().start_queue().await })
-                .unwrap();
+            commands.send(Command::StartQueue);
         }
     }
 
-    fn ui_selection_searching(&mut self, ui: &mut egui::Ui, lcu: &LCU) {
-        let button = ui.add(
+    fn ui_selection_searching(&mut self, ui: &mut egui::Ui, commands: &mut CommandQueue) {
+        let pending = commands.is_pending(|c| matches!(c, Command::StopQueue));
+        let button = ui.add_enabled(
+            !pending,
             egui::Button::new(
-                egui::RichText::new("Cancel")
+                egui::RichText::new(pending_text("Cancel", pending))
                     .text_style(egui::TextStyle::Heading)
                     .color(crate::ui::colors::YELLOW_A800),
             )
@@ -403,89 +677,162 @@ impl Game {
         );
 
         if button.clicked() {
-            crate::RT
-                .block_on(async { lcu.lobby().stop_queue().await })
-                .unwrap();
+            commands.send(Command::StopQueue);
         }
     }
 
-    fn ui_selection_found(&mut self, ui: &mut egui::Ui, lcu: &LCU) {
-        let accept_button = ui.add(
+    fn ui_selection_found(&mut self, ui: &mut egui::Ui, commands: &mut CommandQueue) {
+        let accepting = commands.is_pending(|c| matches!(c, Command::Accept));
+        let declining = commands.is_pending(|c| matches!(c, Command::Decline));
+
+        if let Some(at) = self.auto_accept_at {
+            let left = at.saturating_duration_since(Instant::now());
+            ui.vertical(|ui| {
+                ui.label(format!("Accepting in {}s", left.as_secs() + 1));
+                if ui.button("Cancel").clicked() {
+                    self.auto_accept_at = None;
+                }
+            });
+        }
+
+        let accept_button = ui.add_enabled(
+            !accepting && !declining,
             egui::Button::new(
-                egui::RichText::new("Accept")
+                egui::RichText::new(pending_text("Accept", accepting))
                     .text_style(egui::TextStyle::Heading)
                     .color(crate::ui::colors::YELLOW_A800),
             )
             .fill(crate::ui::colors::INDIGO_A700),
         );
 
-        let decline_button = ui.add(egui::Button::new(
-            egui::RichText::new("Decline")
-                .text_style(egui::TextStyle::Heading)
-                .color(crate::ui::colors::RED_A500),
-        ));
+        let decline_button = ui.add_enabled(
+            !accepting && !declining,
+            egui::Button::new(
+                egui::RichText::new(pending_text("Decline", declining))
+                    .text_style(egui::TextStyle::Heading)
+                    .color(crate::ui::colors::RED_A500),
+            ),
+        );
 
         if accept_button.clicked() {
-            crate::RT
-                .block_on(async { lcu.matchmaking().accept().await })
-                .unwrap();
+            commands.send(Command::Accept);
         }
 
         if decline_button.clicked() {
-            crate::RT
-                .block_on(async { lcu.matchmaking().decline().await })
-                .unwrap();
+            commands.send(Command::Decline);
         }
     }
 
-    fn ui_selection_champ_select(&mut self, ui: &mut egui::Ui, lcu: &LCU) {
-        let button = ui.add(
+    fn ui_selection_champ_select(&mut self, ui: &mut egui::Ui, commands: &mut CommandQueue) {
+        if let Some(champ_select) = &self.champ_select {
+            let mut phase = champ_select.phase().to_string();
+            if let Some(left) = champ_select.time_left(Instant::now()) {
+                phase = format!("{} {}s", phase, left.as_secs());
+            }
+            if champ_select.my_action().is_some() {
+                phase = format!("{} - your turn", phase);
+            }
+            ui.label(phase);
+        }
+
+        let pending = commands.is_pending(|c| matches!(c, Command::Dodge));
+        let button = ui.add_enabled(
+            !pending,
             egui::Button::new(
-                egui::RichText::new("Dodge")
+                egui::RichText::new(pending_text("Dodge", pending))
                     .text_style(egui::TextStyle::Heading)
                     .color(egui::Color32::WHITE),
             )
             .fill(crate::ui::colors::RED_A500),
         );
 
-        let opgg_button = ui.add(
+        // not wired up yet
+        ui.add(
             egui::Button::new(
                 egui::RichText::new("op.gg")
                     .text_style(egui::TextStyle::Heading)
                     .color(egui::Color32::WHITE),
             )
-                .fill(crate::ui::colors::BLUE_A400)
+            .fill(crate::ui::colors::BLUE_A400),
         );
 
-
-
-
         if button.clicked() {
-            crate::RT
-                .block_on(async { lcu.login().dodge_lobby().await })
-                .unwrap();
+            commands.send(Command::Dodge);
         }
     }
 
     fn ui_selection_in_game(&self) {}
 
-    fn ui_after_game_lobby(&mut self, ui: &mut egui::Ui, _lcu: &LCU) {
-        let button = ui.add(
-            egui::Button::new(
-                egui::RichText::new("Play Again")
-                    .text_style(egui::TextStyle::Heading)
-                    .color(crate::ui::colors::YELLOW_A800),
-            )
-            .fill(crate::ui::colors::INDIGO_A700),
-        );
+    fn ui_auto_accept(&mut self, ui: &mut egui::Ui) {
+        let config = &mut self.config;
+        ui.checkbox(&mut config.auto_accept, "Accept found matches");
+        ui.add(egui::Slider::new(&mut config.auto_accept_delay, 0..=10).text("s delay"));
+        ui.checkbox(&mut config.auto_accept_while_away, "Also while away");
 
-        if button.clicked() {
-            self.search_sate = SearchState::None;
+        ui.label("Only in (all if none):");
+        ui.horizontal_wrapped(|ui| {
+            for (queue_id, _, name) in queues::KNOWN {
+                let mut checked = config.auto_accept_queues.contains(&queue_id);
+                if ui.checkbox(&mut checked, name).changed() {
+                    if checked {
+                        config.auto_accept_queues.push(queue_id);
+                    } else {
+                        config.auto_accept_queues.retain(|id| *id != queue_id);
+                    }
+                }
+            }
+        });
+
+        if !self.auto_accepted.is_empty() {
+            ui.separator();
+        }
+        for accepted in self.auto_accepted.iter().rev() {
+            let queue = match accepted.queue_id {
+                Some(queue_id) => self.queues.name(queue_id),
+                None => "Unknown queue".to_string(),
+            };
+            ui.label(
+                egui::RichText::new(format!("{} {}", accepted.time.format("%H:%M:%S"), queue))
+                    .text_style(egui::TextStyle::Small),
+            );
         }
     }
 
-    fn ui_selection_error(&mut self, ui: &mut egui::Ui, _lcu: &LCU) {
-        let button = ui.add(
+    /// Both only ask the client, the state follows once it moved.
+    fn ui_after_game_lobby(&mut self, ui: &mut egui::Ui, commands: &mut CommandQueue) {
+        let pending =
+            commands.is_pending(|c| matches!(c, Command::JoinLobby(..) | Command::LeaveLobby));
+        ui.horizontal(|ui| {
+            let button = ui.add_enabled(
+                !pending && self.last_queue_id.is_some(),
+                egui::Button::new(
+                    egui::RichText::new("Play Again")
+                        .text_style(egui::TextStyle::Heading)
+                        .color(crate::ui::colors::YELLOW_A800),
+                )
+                .fill(crate::ui::colors::INDIGO_A700),
+            );
+            if button.clicked() {
+                if let Some(queue_id) = self.last_queue_id {
+                    commands.send(Command::JoinLobby(queue_id, self.positions.clone()));
+                }
+            }
+
+            self.ui_home_button(ui, commands);
+        });
+    }
+
+    fn ui_selection_error(&mut self, ui: &mut egui::Ui, commands: &mut CommandQueue) {
+        ui.vertical(|ui| {
+            ui.label("The game ended with an error");
+            self.ui_home_button(ui, commands);
+        });
+    }
+
+    fn ui_home_button(&self, ui: &mut egui::Ui, commands: &mut CommandQueue) {
+        let pending = commands.is_pending(|c| matches!(c, Command::LeaveLobby));
+        let button = ui.add_enabled(
+            !pending,
             egui::Button::new(
                 egui::RichText::new("Home")
                     .text_style(egui::TextStyle::Heading)
@@ -495,44 +842,174 @@ impl Game {
         );
 
         if button.clicked() {
-            self.search_sate = SearchState::None;
+            commands.send(Command::LeaveLobby);
         }
     }
 
-    fn join_lobby_buttons(&mut self, ui: &mut egui::Ui, lcu: &LCU) {
-        egui::Grid::new("lobby_buttons")
-            .spacing(egui::Vec2::new(5.0, 5.0))
-            .show(ui, |ui| {
-                self.ui_game_button(ui, lcu, QueueId::Solo, "Solo");
-                self.ui_game_button(ui, lcu, QueueId::Draft, "Draft");
-                ui.end_row();
-                self.ui_game_button(ui, lcu, QueueId::Flex, "Flex");
-                self.ui_game_button(ui, lcu, QueueId::Blind, "Blind");
-                ui.end_row();
-                self.ui_game_button(ui, lcu, QueueId::Clash, "Clash");
-                self.ui_game_button(ui, lcu, QueueId::Aram, "Aram");
-                ui.end_row();
+    fn ui_inbox(&self, ui: &mut egui::Ui, commands: &mut CommandQueue) {
+        let pending = self.inbox.pending();
+        if pending.is_empty() {
+            ui.label(egui::RichText::new("No invitations").text_style(egui::TextStyle::Small));
+        }
+        for invitation in pending {
+            ui.horizontal(|ui| {
+                let received = self
+                    .inbox
+                    .received(invitation)
+                    .map_or(String::new(), |time| time.format("%H:%M").to_string());
+                ui.label(egui::RichText::new(received).text_style(egui::TextStyle::Small));
+                ui.label(invitation_text(invitation, &self.queues));
+                ui_invitation_buttons(ui, commands, invitation);
+            });
+        }
+    }
+
+    /// A popup per unanswered invitation until it's answered or closed.
+    fn ui_invitation_notification(&mut self, ctx: &egui::CtxRef, commands: &mut CommandQueue) {
+        let mut dismissed = vec![];
+        egui::Area::new("invitations")
+            .anchor(egui::Align2::RIGHT_TOP, egui::Vec2::new(-270.0, 10.0))
+            .show(ctx, |ui| {
+                for invitation in self.inbox.unseen() {
+                    egui::Frame::popup(ui.style()).show(ui, |ui| {
+                        ui.set_max_width(320.0);
+                        ui.horizontal(|ui| {
+                            ui.label(
+                                egui::RichText::new("Invitation")
+                                    .text_style(egui::TextStyle::Body)
+                                    .color(crate::ui::colors::LIGHT_BLUE_A400),
+                            );
+                            if ui.small_button("x").clicked() {
+                                dismissed.push(invitation.invitation_id.clone());
+                            }
+                        });
+                        ui.label(invitation_text(invitation, &self.queues));
+                        ui.horizontal(|ui| {
+                            ui_invitation_buttons(ui, commands, invitation);
+                        });
+                    });
+                    ui.add_space(5.0);
+                }
             });
+        for invitation_id in dismissed {
+            self.inbox.dismiss(&invitation_id);
+        }
     }
 
-    fn ui_role_buttons(&mut self, ui: &mut egui::Ui, lcu: &LCU, textures: &TextureManager) {
+    fn ui_timeline(&self, ui: &mut egui::Ui) {
+        let session = self.flow.session();
+        if session.is_empty() {
+            ui.label(egui::RichText::new("Nothing yet").text_style(egui::TextStyle::Small));
+        }
+        for (i, entry) in session.iter().enumerate() {
+            let mut text = format!("{} {}", entry.entered.format("%H:%M:%S"), entry.phase);
+            // the current phase has no end yet
+            if let Some(next) = session.get(i + 1) {
+                text = format!(
+                    "{} ({}s)",
+                    text,
+                    (next.entered - entry.entered).num_seconds()
+                );
+            }
+            let mut text = egui::RichText::new(text).text_style(egui::TextStyle::Small);
+            if !entry.expected {
+                text = text.color(crate::ui::colors::YELLOW_A800);
+            }
+            ui.label(text);
+        }
+    }
+
+    /// The client's queues by group, the queues we know of until its list is loaded.
+    fn join_lobby_buttons(&mut self, ui: &mut egui::Ui, commands: &mut CommandQueue) {
+        ui.vertical(|ui| {
+            if !self.queues.is_loaded() {
+                egui::Grid::new("lobby_buttons")
+                    .spacing(egui::Vec2::new(5.0, 5.0))
+                    .show(ui, |ui| {
+                        for (i, (_, queue_id, name)) in queues::KNOWN.iter().enumerate() {
+                            self.ui_game_button(ui, commands, *queue_id, *name, None);
+                            if i % 2 == 1 {
+                                ui.end_row();
+                            }
+                        }
+                    });
+                return;
+            }
+
+            // cloned so the buttons can borrow `self` mutably
+            let groups = queues::GROUPS
+                .iter()
+                .map(|group| {
+                    let buttons = self
+                        .queues
+                        .group(group)
+                        .map(|queue| (queue.clone(), self.queues.unavailable(queue)))
+                        .collect::<Vec<(GameQueue, Option<String>)>>();
+                    (*group, buttons)
+                })
+                .filter(|(_, buttons)| !buttons.is_empty())
+                .collect::<Vec<_>>();
+
+            egui::ScrollArea::vertical()
+                .max_height(200.0)
+                .show(ui, |ui| {
+                    for (group, buttons) in groups {
+                        // the commonly played groups start open
+                        let open = group == queues::GROUPS[0] || group == queues::GROUPS[1];
+                        egui::CollapsingHeader::new(group)
+                            .default_open(open)
+                            .show(ui, |ui| {
+                                ui.horizontal_wrapped(|ui| {
+                                    for (queue, unavailable) in buttons {
+                                        self.ui_game_button(
+                                            ui,
+                                            commands,
+                                            queue.id,
+                                            queue.label(),
+                                            unavailable,
+                                        );
+                                    }
+                                });
+                            });
+                    }
+                });
+
+            let pending = commands.is_pending(|c| matches!(c, Command::LoadQueues));
+            if ui
+                .add_enabled(
+                    !pending,
+                    egui::Button::new(pending_text("Refresh", pending)),
+                )
+                .clicked()
+            {
+                commands.send(Command::LoadQueues);
+            }
+        });
+    }
+
+    fn ui_role_buttons(
+        &mut self,
+        ui: &mut egui::Ui,
+        commands: &mut CommandQueue,
+        textures: &TextureManager,
+    ) {
         egui::Grid::new("roles")
             .spacing(egui::Vec2::splat(5.0))
             .show(ui, |ui| {
-                self.ui_role_button(ui, lcu, Position::TOP, "top", textures);
-                self.ui_role_button(ui, lcu, Position::JUNGLE, "jungle", textures);
-                self.ui_role_button(ui, lcu, Position::MIDDLE, "middle", textures);
+                self.ui_role_button(ui, commands, Position::TOP, "top", textures);
+                self.ui_role_button(ui, commands, Position::JUNGLE, "jungle", textures);
+                self.ui_role_button(ui, commands, Position::MIDDLE, "middle", textures);
                 ui.end_row();
-                self.ui_role_button(ui, lcu, Position::BOTTOM, "bottom", textures);
-                self.ui_role_button(ui, lcu, Position::UTILITY, "utility", textures);
-                self.ui_role_button(ui, lcu, Position::FILL, "fill", textures);
+                self.ui_role_button(ui, commands, Position::BOTTOM, "bottom", textures);
+                self.ui_role_button(ui, commands, Position::UTILITY, "utility", textures);
+                self.ui_role_button(ui, commands, Position::FILL, "fill", textures);
             });
     }
 
     fn ui_role_button(
         &mut self,
         ui: &mut egui::Ui,
-        lcu: &LCU,
+        commands: &mut CommandQueue,
         position: Position,
         img: &str,
         textures: &TextureManager,
@@ -554,7 +1031,219 @@ impl Game {
         let response = ui.add(button);
 
         if response.clicked() {
-            self.update_position(lcu, position);
+            self.update_position(commands, position);
         }
     }
 }
+
+/// Button text with a marker while its request is in flight.
+fn pending_text(text: &str, pending: bool) -> String {
+    if pending {
+        format!("{}...", text)
+    } else {
+        text.to_string()
+    }
+}
+
+/// The conversation of `kind` in a collapsible panel, nothing until the chat created it.
+fn ui_chat(ui: &mut egui::Ui, commands: &mut CommandQueue, chat: &mut Chat, kind: &str) {
+    let room = match chat.room(kind) {
+        Some(room) => room,
+        None => return,
+    };
+    ui.vertical(|ui| {
+        ui.set_max_width(300.0);
+        egui::CollapsingHeader::new("Chat")
+            .id_source(kind)
+            .default_open(true)
+            .show(ui, |ui| {
+                room.ui(ui, commands);
+            });
+    });
+}
+
+/// e.g. `Teemo Main invited you to Ranked Solo/Duo`.
+fn invitation_text(invitation: &ReceivedInvitation, queues: &QueueList) -> String {
+    let queue = if invitation.game_config.is_custom {
+        "a custom game".to_string()
+    } else {
+        queues.name(invitation.game_config.queue_id)
+    };
+    format!("{} invited you to {}", invitation.from_summoner_name, queue)
+}
+
+/// Accept and Decline, both disabled while either is in flight.
+fn ui_invitation_buttons(
+    ui: &mut egui::Ui,
+    commands: &mut CommandQueue,
+    invitation: &ReceivedInvitation,
+) {
+    let id = &invitation.invitation_id;
+    let accepting =
+        commands.is_pending(|c| matches!(c, Command::AcceptInvitation(pending) if pending == id));
+    let declining =
+        commands.is_pending(|c| matches!(c, Command::DeclineInvitation(pending) if pending == id));
+
+    let mut accept = ui.add_enabled(
+        !accepting && !declining && invitation.can_accept_invitation,
+        egui::Button::new(pending_text("Accept", accepting)),
+    );
+    if !invitation.can_accept_invitation {
+        accept = accept.on_disabled_hover_text("The client doesn't allow joining right now");
+    }
+    if accept.clicked() {
+        commands.send(Command::AcceptInvitation(id.clone()));
+    }
+    if ui
+        .add_enabled(
+            !accepting && !declining,
+            egui::Button::new(pending_text("Decline", declining)),
+        )
+        .clicked()
+    {
+        commands.send(Command::DeclineInvitation(id.clone()));
+    }
+}
+
+/// The rank for the lobby's queue, the other queues below it.
+fn ui_ranked(ui: &mut egui::Ui, lookup: Lookup, main_queue: &str) {
+    let stats = match lookup {
+        Lookup::Loaded(stats) => stats,
+        Lookup::Loading => {
+            ui.label(egui::RichText::new("Loading rank...").color(crate::ui::colors::GRAY_A500));
+            return;
+        }
+        Lookup::Failed => {
+            ui.label(egui::RichText::new("Rank unavailable").color(crate::ui::colors::GRAY_A500));
+            return;
+        }
+    };
+
+    let main = match stats.queue(main_queue) {
+        Some(queue) => {
+            let mut parts = vec![format!("{} {}", queue.queue_name(), queue.rank())];
+            parts.extend(queue.series());
+            parts.extend(queue.record());
+            parts.extend(queue.previous_season());
+            parts.join(" | ")
+        }
+        None => format!("{} Unranked", ranked::queue_name(main_queue)),
+    };
+    ui.label(main);
+
+    let others = stats
+        .others(main_queue)
+        .iter()
+        .map(|queue| format!("{} {}", queue.queue_name(), queue.rank()))
+        .collect::<Vec<_>>();
+    if !others.is_empty() {
+        ui.label(
+            egui::RichText::new(others.join(" · "))
+                .text_style(egui::TextStyle::Small)
+                .color(crate::ui::colors::GRAY_A500),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ui::game::champ_select::ChampSelectSession;
+
+    fn phase(name: &str) -> GameFlowPhase {
+        serde_json::from_value(serde_json::Value::from(name)).unwrap()
+    }
+
+    /// In queue for `in_queue` seconds as of `now`.
+    fn searching(now: Instant, in_queue: f64) -> Game {
+        let mut game = Game::new(GameConfig::default());
+        game.flow.advance(&phase("Lobby"), Local::now());
+        game.flow.advance(&phase("Matchmaking"), Local::now());
+        game.queue_timer = Some(in_queue);
+        game.queue_timer_updated = Some(now);
+        game
+    }
+
+    fn champ_select(now: Instant, left_ms: i64) -> ChampSelect {
+        let mut session = ChampSelectSession::default();
+        session.timer.adjusted_time_left_in_phase = left_ms;
+        ChampSelect {
+            session,
+            updated: now,
+        }
+    }
+
+    #[test]
+    fn idle_needs_no_repaint() {
+        let game = Game::new(GameConfig::default());
+        assert_eq!(game.next_repaint(Instant::now()), None);
+    }
+
+    #[test]
+    fn queue_timer_ticks_on_whole_seconds() {
+        let now = Instant::now();
+        let game = searching(now, 12.25);
+        assert_eq!(
+            game.next_repaint(now),
+            Some(now + Duration::from_millis(750))
+        );
+
+        // half a second later the same second is still shown
+        let later = now + Duration::from_millis(500);
+        assert_eq!(
+            game.next_repaint(later),
+            Some(later + Duration::from_millis(250))
+        );
+    }
+
+    #[test]
+    fn queue_timer_only_ticks_while_searching() {
+        let now = Instant::now();
+        let mut game = searching(now, 12.25);
+        game.flow.advance(&phase("Lobby"), Local::now());
+        assert_eq!(game.next_repaint(now), None);
+    }
+
+    #[test]
+    fn ready_check_counts_down() {
+        let now = Instant::now();
+        let mut game = Game::new(GameConfig::default());
+        game.auto_accept_at = Some(now + Duration::from_millis(2500));
+        assert_eq!(
+            game.next_repaint(now),
+            Some(now + Duration::from_millis(500))
+        );
+
+        // accepted, nothing left to count
+        assert_eq!(game.next_repaint(now + Duration::from_secs(3)), None);
+    }
+
+    #[test]
+    fn champ_select_timer_counts_down() {
+        let now = Instant::now();
+        let mut game = Game::new(GameConfig::default());
+        game.champ_select = Some(champ_select(now, 10_400));
+        assert_eq!(
+            game.next_repaint(now),
+            Some(now + Duration::from_millis(400))
+        );
+    }
+
+    #[test]
+    fn earliest_clock_wins() {
+        let now = Instant::now();
+        let mut game = searching(now, 12.25);
+        game.auto_accept_at = Some(now + Duration::from_millis(2500));
+        game.champ_select = Some(champ_select(now, 10_400));
+        assert_eq!(
+            game.next_repaint(now),
+            Some(now + Duration::from_millis(400))
+        );
+
+        game.champ_select = None;
+        assert_eq!(
+            game.next_repaint(now),
+            Some(now + Duration::from_millis(500))
+        );
+    }
+}
@@ -0,0 +1,338 @@
+//! `/lol-champ-select/v1/session` as the client sends it, plus what we keep between updates.
+
+use kassadin::types::socket::EventType;
+use serde::Deserialize;
+use std::fmt::{Display, Formatter};
+use std::time::{Duration, Instant};
+
+pub const SESSION: &str = "/lol-champ-select/v1/session";
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ChampSelectSession {
+    pub local_player_cell_id: i64,
+    pub my_team: Vec<ChampSelectPlayer>,
+    pub their_team: Vec<ChampSelectPlayer>,
+    /// groups of actions that run at the same time, e.g. all bans of a team
+    pub actions: Vec<Vec<ChampSelectAction>>,
+    pub bans: ChampSelectBans,
+    pub timer: ChampSelectTimer,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ChampSelectPlayer {
+    pub cell_id: i64,
+    pub summoner_id: i64,
+    /// empty in blind pick and for the enemy team
+    pub assigned_position: String,
+    /// 0 until locked in
+    pub champion_id: i64,
+    /// hovered champion, 0 if none
+    pub champion_pick_intent: i64,
+    pub spell1_id: u64,
+    pub spell2_id: u64,
+    pub team: i64,
+}
+
+#[derive(Debug, Clone, PartialEq, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ActionKind {
+    Ban,
+    Pick,
+    TenBansReveal,
+    #[default]
+    #[serde(other)]
+    Other,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ChampSelectAction {
+    pub id: i64,
+    pub actor_cell_id: i64,
+    pub champion_id: i64,
+    #[serde(rename = "type")]
+    pub kind: ActionKind,
+    pub completed: bool,
+    pub is_ally_action: bool,
+    pub is_in_progress: bool,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ChampSelectBans {
+    pub my_team_bans: Vec<i64>,
+    pub their_team_bans: Vec<i64>,
+    pub num_bans: i64,
+}
+
+#[derive(Debug, Clone, PartialEq, Default, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum ChampSelectPhase {
+    Planning,
+    BanPick,
+    Finalization,
+    GameStarting,
+    #[default]
+    #[serde(other)]
+    Unknown,
+}
+
+impl Display for ChampSelectPhase {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let phase = match self {
+            Self::Planning => "Planning",
+            Self::BanPick => "Ban & Pick",
+            Self::Finalization => "Finalization",
+            Self::GameStarting => "Game starting",
+            Self::Unknown => "",
+        };
+        write!(f, "{}", phase)
+    }
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ChampSelectTimer {
+    pub phase: ChampSelectPhase,
+    /// milliseconds, as of when the update was sent
+    pub adjusted_time_left_in_phase: i64,
+    pub total_time_in_phase: i64,
+    pub is_infinite: bool,
+}
+
+/// The current champ select, kept up to date by every session event.
+#[derive(Debug, Clone)]
+pub struct ChampSelect {
+    pub session: ChampSelectSession,
+    /// when `session.timer` was last set, the client only sends updates when something changes
+    pub updated: Instant,
+}
+
+impl ChampSelect {
+    pub fn new(session: ChampSelectSession) -> Self {
+        Self {
+            session,
+            updated: Instant::now(),
+        }
+    }
+
+    /// Apply a session event, `None` once champ select is over.
+    pub fn apply(
+        champ_select: Option<Self>,
+        event_type: Option<EventType>,
+        session: Option<ChampSelectSession>,
+    ) -> Option<Self> {
+        match (event_type, session) {
+            (Some(EventType::Delete), _) => None,
+            (_, Some(session)) => match champ_select {
+                Some(mut champ_select) => {
+                    champ_select.update(session);
+                    Some(champ_select)
+                }
+                None => Some(Self::new(session)),
+            },
+            (_, None) => champ_select,
+        }
+    }
+
+    /// Take a newer session, the timer only restarts if the phase or its time left changed.
+    pub fn update(&mut self, session: ChampSelectSession) {
+        let timer = &self.session.timer;
+        if timer.phase != session.timer.phase
+            || timer.adjusted_time_left_in_phase != session.timer.adjusted_time_left_in_phase
+        {
+            self.updated = Instant::now();
+        }
+        self.session = session;
+    }
+
+    pub fn phase(&self) -> &ChampSelectPhase {
+        &self.session.timer.phase
+    }
+
+    /// `None` if the phase has no time limit.
+    pub fn time_left(&self, now: Instant) -> Option<Duration> {
+        let timer = &self.session.timer;
+        if timer.is_infinite {
+            return None;
+        }
+        let left = Duration::from_millis(timer.adjusted_time_left_in_phase.max(0) as u64);
+        Some(left.saturating_sub(now.saturating_duration_since(self.updated)))
+    }
+
+    pub fn local_player(&self) -> Option<&ChampSelectPlayer> {
+        self.player(self.session.local_player_cell_id)
+    }
+
+    pub fn player(&self, cell_id: i64) -> Option<&ChampSelectPlayer> {
+        self.session
+            .my_team
+            .iter()
+            .chain(&self.session.their_team)
+            .find(|player| player.cell_id == cell_id)
+    }
+
+    pub fn is_ally(&self, cell_id: i64) -> bool {
+        self.session
+            .my_team
+            .iter()
+            .any(|player| player.cell_id == cell_id)
+    }
+
+    pub fn actions(&self) -> impl Iterator<Item = &ChampSelectAction> {
+        self.session.actions.iter().flatten()
+    }
+
+    /// Actions waiting on someone right now.
+    pub fn current_actions(&self) -> impl Iterator<Item = &ChampSelectAction> {
+        self.actions().filter(|action| action.is_in_progress)
+    }
+
+    /// The local player's ban or pick that is in progress, if it's their turn.
+    pub fn my_action(&self) -> Option<&ChampSelectAction> {
+        let cell_id = self.session.local_player_cell_id;
+        self.current_actions()
+            .find(|action| action.actor_cell_id == cell_id && !action.completed)
+    }
+
+    /// Bans that are locked in.
+    pub fn bans(&self) -> impl Iterator<Item = &ChampSelectAction> {
+        self.actions()
+            .filter(|action| action.kind == ActionKind::Ban && action.completed)
+    }
+
+    pub fn picks(&self) -> impl Iterator<Item = &ChampSelectAction> {
+        self.actions()
+            .filter(|action| action.kind == ActionKind::Pick && action.completed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    /// Cut down from a draft pick session, the local player is in cell 1 and it's their turn to ban.
+    fn session(time_left: i64) -> ChampSelectSession {
+        serde_json::from_value(json!({
+            "localPlayerCellId": 1,
+            "myTeam": [
+                {"cellId": 0, "summonerId": 10, "assignedPosition": "top", "championId": 0,
+                 "championPickIntent": 86, "spell1Id": 4, "spell2Id": 12, "team": 1},
+                {"cellId": 1, "summonerId": 11, "assignedPosition": "jungle", "championId": 0,
+                 "championPickIntent": 0, "spell1Id": 4, "spell2Id": 11, "team": 1},
+            ],
+            "theirTeam": [
+                {"cellId": 5, "summonerId": 0, "assignedPosition": "", "championId": 0, "team": 2},
+            ],
+            "actions": [
+                [
+                    {"id": 1, "actorCellId": 0, "championId": 157, "type": "ban",
+                     "completed": true, "isAllyAction": true, "isInProgress": true},
+                    {"id": 2, "actorCellId": 1, "championId": 0, "type": "ban",
+                     "completed": false, "isAllyAction": true, "isInProgress": true},
+                    {"id": 3, "actorCellId": 5, "championId": 0, "type": "ban",
+                     "completed": false, "isAllyAction": false, "isInProgress": true},
+                ],
+                [{"id": 4, "type": "ten_bans_reveal", "completed": false}],
+                [
+                    {"id": 5, "actorCellId": 1, "championId": 0, "type": "pick",
+                     "completed": false, "isAllyAction": true, "isInProgress": false},
+                ],
+            ],
+            "bans": {"myTeamBans": [], "theirTeamBans": [], "numBans": 6},
+            "timer": {"phase": "BAN_PICK", "adjustedTimeLeftInPhase": time_left,
+                      "totalTimeInPhase": 32000, "isInfinite": false, "internalNowInEpochMs": 0},
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn sessions_decode() {
+        let champ_select = ChampSelect::new(session(30_000));
+        assert_eq!(champ_select.phase(), &ChampSelectPhase::BanPick);
+        assert_eq!(champ_select.local_player().unwrap().summoner_id, 11);
+        assert!(champ_select.is_ally(0));
+        assert!(!champ_select.is_ally(5));
+        assert_eq!(champ_select.player(5).unwrap().team, 2);
+        let kinds = champ_select
+            .actions()
+            .map(|action| action.kind.clone())
+            .collect::<Vec<_>>();
+        let (ban, pick) = (ActionKind::Ban, ActionKind::Pick);
+        assert_eq!(
+            kinds,
+            [
+                ban.clone(),
+                ban.clone(),
+                ban,
+                ActionKind::TenBansReveal,
+                pick
+            ]
+        );
+        assert_eq!(champ_select.bans().count(), 1);
+        assert_eq!(champ_select.picks().count(), 0);
+    }
+
+    #[test]
+    fn my_action_is_the_local_players_turn() {
+        let mut champ_select = ChampSelect::new(session(30_000));
+        assert_eq!(champ_select.current_actions().count(), 3);
+        assert_eq!(champ_select.my_action().unwrap().id, 2);
+
+        // banned, the pick isn't in progress yet
+        champ_select.session.actions[0][1].completed = true;
+        assert!(champ_select.my_action().is_none());
+        champ_select.session.actions[0]
+            .iter_mut()
+            .for_each(|action| action.is_in_progress = false);
+        champ_select.session.actions[2][0].is_in_progress = true;
+        assert_eq!(champ_select.my_action().unwrap().kind, ActionKind::Pick);
+    }
+
+    #[test]
+    fn time_left_counts_down_from_the_last_update() {
+        let mut champ_select = ChampSelect::new(session(30_000));
+        let updated = champ_select.updated;
+        let later = |secs| updated + Duration::from_secs(secs);
+        assert_eq!(
+            champ_select.time_left(updated),
+            Some(Duration::from_secs(30))
+        );
+        assert_eq!(
+            champ_select.time_left(later(10)),
+            Some(Duration::from_secs(20))
+        );
+        assert_eq!(champ_select.time_left(later(40)), Some(Duration::ZERO));
+
+        // the same timer again doesn't restart the countdown
+        champ_select.update(session(30_000));
+        assert_eq!(champ_select.updated, updated);
+        champ_select.update(session(12_000));
+        assert!(champ_select.updated >= updated);
+        let now = champ_select.updated;
+        assert_eq!(champ_select.time_left(now), Some(Duration::from_secs(12)));
+
+        champ_select.session.timer.is_infinite = true;
+        assert_eq!(champ_select.time_left(now), None);
+    }
+
+    #[test]
+    fn apply_follows_the_session_events() {
+        let champ_select = ChampSelect::apply(None, Some(EventType::Create), Some(session(30_000)));
+        assert!(champ_select.is_some());
+        // an update without a body keeps what we had
+        let champ_select = ChampSelect::apply(champ_select, Some(EventType::Update), None);
+        let updated = champ_select.as_ref().unwrap().updated;
+        let champ_select =
+            ChampSelect::apply(champ_select, Some(EventType::Update), Some(session(30_000)));
+        assert_eq!(champ_select.as_ref().unwrap().updated, updated);
+        let champ_select =
+            ChampSelect::apply(champ_select, Some(EventType::Delete), Some(session(0)));
+        assert!(champ_select.is_none());
+        // hydrated without an event type
+        assert!(ChampSelect::apply(None, None, Some(session(30_000))).is_some());
+    }
+}
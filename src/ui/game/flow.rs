@@ -0,0 +1,315 @@
+//! The gameflow phases the client goes through, as a state machine that only the client moves.
+//! Every phase entered is kept with its time, the ui shows them as the session's timeline.
+
+use chrono::{DateTime, Duration, Local};
+use kassadin::types::socket::GameFlowPhase;
+
+/// entries kept, older ones are dropped
+const MAX_TIMELINE: usize = 100;
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum SearchState {
+    #[default]
+    None,
+    Lobby,
+    Searching,
+    Found,
+    ChampSelect,
+    InGame,
+    /// waiting for stats after the game
+    AfterGameLobby,
+    /// honor and stats screens
+    EndOfGame,
+    Error,
+}
+
+impl SearchState {
+    pub fn from_phase(phase: &GameFlowPhase) -> Self {
+        match phase {
+            GameFlowPhase::None => Self::None,
+            GameFlowPhase::Lobby => Self::Lobby,
+            GameFlowPhase::Matchmaking => Self::Searching,
+            GameFlowPhase::ReadyCheck => Self::Found,
+            GameFlowPhase::ChampSelect => Self::ChampSelect,
+            GameFlowPhase::GameStart | GameFlowPhase::InProgress => Self::InGame,
+            GameFlowPhase::WaitingForStats => Self::AfterGameLobby,
+            GameFlowPhase::PreEndOfGame | GameFlowPhase::EndOfGame => Self::EndOfGame,
+            GameFlowPhase::TerminatedInError => Self::Error,
+        }
+    }
+
+    /// Whether the client goes from `self` to `next` in a normal session.
+    pub fn can_enter(self, next: Self) -> bool {
+        use SearchState::*;
+
+        match (self, next) {
+            (from, to) if from == to => true,
+            // leaving, failing and going back to the lobby work from everywhere
+            (_, None) | (_, Error) | (_, Lobby) => true,
+            (Lobby, Searching) => true,
+            (Searching, Found) => true,
+            // declined by someone, or someone dodged: back into the queue
+            (Found, Searching) | (ChampSelect, Searching) => true,
+            (Found, ChampSelect) => true,
+            (ChampSelect, InGame) => true,
+            (InGame, AfterGameLobby) | (InGame, EndOfGame) => true,
+            (AfterGameLobby, EndOfGame) => true,
+            _ => false,
+        }
+    }
+}
+
+/// One phase of the timeline.
+#[derive(Debug, Clone)]
+pub struct FlowEntry {
+    /// as the client calls it, several phases can map to the same state
+    pub phase: String,
+    pub state: SearchState,
+    pub entered: DateTime<Local>,
+    /// `false` if the previous state can't lead here, e.g. because an event got lost
+    pub expected: bool,
+}
+
+/// What `GameFlow::advance` made of a phase.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Transition {
+    /// the same phase again
+    Unchanged,
+    Entered,
+    /// entered anyway, the client knows better, but it's not a transition we know
+    Unexpected {
+        from: SearchState,
+    },
+}
+
+#[derive(Debug, Default)]
+pub struct GameFlow {
+    timeline: Vec<FlowEntry>,
+}
+
+impl GameFlow {
+    pub fn state(&self) -> SearchState {
+        self.timeline
+            .last()
+            .map_or(SearchState::None, |entry| entry.state)
+    }
+
+    /// When the current phase was entered, `None` before the client told us any.
+    pub fn entered(&self) -> Option<DateTime<Local>> {
+        self.timeline.last().map(|entry| entry.entered)
+    }
+
+    /// How long the current phase lasts so far.
+    pub fn time_in_phase(&self, now: DateTime<Local>) -> Option<Duration> {
+        self.entered().map(|entered| now - entered)
+    }
+
+    /// Everything since connecting, oldest first.
+    pub fn timeline(&self) -> &[FlowEntry] {
+        &self.timeline
+    }
+
+    /// The phases since the client was last idle, i.e. the current lobby through its game.
+    pub fn session(&self) -> &[FlowEntry] {
+        let start = self
+            .timeline
+            .iter()
+            .rposition(|entry| entry.state == SearchState::None)
+            .map_or(0, |idx| idx + 1);
+        &self.timeline[start..]
+    }
+
+    /// Move to `phase`, entered at `at`.
+    /// The first phase after a reset is always expected, we may have connected mid game.
+    pub fn advance(&mut self, phase: &GameFlowPhase, at: DateTime<Local>) -> Transition {
+        let name = format!("{:?}", phase);
+        if self.timeline.last().map(|entry| &entry.phase) == Some(&name) {
+            return Transition::Unchanged;
+        }
+
+        let from = self.state();
+        let state = SearchState::from_phase(phase);
+        let expected = self.timeline.is_empty() || from.can_enter(state);
+        self.timeline.push(FlowEntry {
+            phase: name,
+            state,
+            entered: at,
+            expected,
+        });
+        if self.timeline.len() > MAX_TIMELINE {
+            self.timeline.remove(0);
+        }
+
+        if expected {
+            Transition::Entered
+        } else {
+            Transition::Unexpected { from }
+        }
+    }
+
+    /// Forget the timeline, e.g. after the client restarted.
+    pub fn reset(&mut self) {
+        self.timeline.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Phases as recorded from `/lol-gameflow/v1/session` over one session each.
+    const NORMAL_GAME: &[&str] = &[
+        "None",
+        "Lobby",
+        "Matchmaking",
+        "ReadyCheck",
+        "ChampSelect",
+        "GameStart",
+        "InProgress",
+        "WaitingForStats",
+        "PreEndOfGame",
+        "EndOfGame",
+        "Lobby",
+    ];
+    const DECLINED_AND_DODGED: &[&str] = &[
+        "Lobby",
+        "Matchmaking",
+        "ReadyCheck",
+        "Matchmaking",
+        "ReadyCheck",
+        "ChampSelect",
+        "Matchmaking",
+        "ReadyCheck",
+        "ChampSelect",
+        "Lobby",
+        "None",
+    ];
+    const CONNECTED_MID_GAME: &[&str] = &["InProgress", "WaitingForStats", "EndOfGame", "None"];
+
+    fn phase(name: &str) -> GameFlowPhase {
+        serde_json::from_value(serde_json::Value::from(name)).unwrap()
+    }
+
+    /// Feeds `phases` one second apart, returning what each did.
+    fn replay(flow: &mut GameFlow, phases: &[&str]) -> Vec<Transition> {
+        let start = Local::now();
+        phases
+            .iter()
+            .enumerate()
+            .map(|(i, name)| flow.advance(&phase(name), start + Duration::seconds(i as i64)))
+            .collect()
+    }
+
+    #[test]
+    fn normal_game_is_expected() {
+        let mut flow = GameFlow::default();
+        let transitions = replay(&mut flow, NORMAL_GAME);
+
+        assert!(transitions.iter().all(|t| *t == Transition::Entered));
+        assert_eq!(flow.state(), SearchState::Lobby);
+        assert_eq!(flow.timeline().len(), NORMAL_GAME.len());
+    }
+
+    #[test]
+    fn end_of_game_phases_are_tracked() {
+        let mut flow = GameFlow::default();
+        replay(&mut flow, &NORMAL_GAME[..9]);
+        assert_eq!(flow.state(), SearchState::EndOfGame);
+
+        // PreEndOfGame and EndOfGame share a state but are separate phases
+        let transition = flow.advance(&phase("EndOfGame"), Local::now());
+        assert_eq!(transition, Transition::Entered);
+        assert_eq!(flow.state(), SearchState::EndOfGame);
+        assert_eq!(flow.timeline().last().unwrap().phase, "EndOfGame");
+    }
+
+    #[test]
+    fn declines_and_dodges_go_back_to_the_queue() {
+        let mut flow = GameFlow::default();
+        let transitions = replay(&mut flow, DECLINED_AND_DODGED);
+
+        assert!(transitions.iter().all(|t| *t == Transition::Entered));
+        assert_eq!(flow.state(), SearchState::None);
+    }
+
+    #[test]
+    fn first_phase_is_always_expected() {
+        let mut flow = GameFlow::default();
+        let transitions = replay(&mut flow, CONNECTED_MID_GAME);
+
+        assert!(transitions.iter().all(|t| *t == Transition::Entered));
+    }
+
+    #[test]
+    fn repeated_phase_is_unchanged() {
+        let mut flow = GameFlow::default();
+        let transitions = replay(&mut flow, &["Lobby", "Lobby", "Matchmaking", "Matchmaking"]);
+
+        assert_eq!(
+            transitions,
+            vec![
+                Transition::Entered,
+                Transition::Unchanged,
+                Transition::Entered,
+                Transition::Unchanged,
+            ]
+        );
+        assert_eq!(flow.timeline().len(), 2);
+    }
+
+    #[test]
+    fn skipped_phases_are_unexpected_but_entered() {
+        let mut flow = GameFlow::default();
+        // the ready check and champ select updates got lost
+        let transitions = replay(&mut flow, &["Lobby", "Matchmaking", "InProgress"]);
+
+        assert_eq!(
+            transitions[2],
+            Transition::Unexpected {
+                from: SearchState::Searching
+            }
+        );
+        assert_eq!(flow.state(), SearchState::InGame);
+        assert!(!flow.timeline()[2].expected);
+    }
+
+    #[test]
+    fn entry_times_are_recorded() {
+        let mut flow = GameFlow::default();
+        let start = Local::now();
+        flow.advance(&phase("Lobby"), start);
+        flow.advance(&phase("Matchmaking"), start + Duration::seconds(30));
+
+        assert_eq!(flow.entered(), Some(start + Duration::seconds(30)));
+        assert_eq!(
+            flow.time_in_phase(start + Duration::seconds(45)),
+            Some(Duration::seconds(15))
+        );
+    }
+
+    #[test]
+    fn session_starts_after_the_last_idle_phase() {
+        let mut flow = GameFlow::default();
+        replay(&mut flow, DECLINED_AND_DODGED);
+        assert!(flow.session().is_empty());
+
+        replay(&mut flow, &["Lobby", "Matchmaking"]);
+        let session = flow
+            .session()
+            .iter()
+            .map(|entry| entry.phase.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(session, vec!["Lobby", "Matchmaking"]);
+    }
+
+    #[test]
+    fn reset_forgets_the_timeline() {
+        let mut flow = GameFlow::default();
+        replay(&mut flow, NORMAL_GAME);
+        flow.reset();
+
+        assert_eq!(flow.state(), SearchState::None);
+        assert!(flow.timeline().is_empty());
+        assert_eq!(flow.entered(), None);
+    }
+}
@@ -0,0 +1,97 @@
+//! Lobby invitations others sent us, from `/lol-lobby/v2/received-invitations`.
+//! The client sends the whole list on every change, declined and accepted ones included for a while.
+
+use crate::ui::game::lobby::LobbyGameConfig;
+use chrono::{DateTime, Local, TimeZone};
+use serde::Deserialize;
+use std::collections::{HashMap, HashSet};
+
+pub const RECEIVED_INVITATIONS: &str = "/lol-lobby/v2/received-invitations";
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ReceivedInvitation {
+    pub invitation_id: String,
+    pub from_summoner_id: i64,
+    pub from_summoner_name: String,
+    /// `Pending`, `Accepted`, `Declined` or `Error`
+    pub state: String,
+    /// milliseconds since the epoch, as a string
+    pub timestamp: String,
+    pub game_config: LobbyGameConfig,
+    /// `false` e.g. while in a game
+    pub can_accept_invitation: bool,
+}
+
+impl ReceivedInvitation {
+    pub fn is_pending(&self) -> bool {
+        self.state == "Pending"
+    }
+
+    /// When the client says it was sent, `None` if it doesn't say.
+    fn sent(&self) -> Option<DateTime<Local>> {
+        let millis = self.timestamp.parse::<i64>().ok()?;
+        Local.timestamp_millis_opt(millis).single()
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Inbox {
+    invitations: Vec<ReceivedInvitation>,
+    /// by invitation id, when it arrived
+    received: HashMap<String, DateTime<Local>>,
+    /// invitations whose notification was closed, they stay in the inbox
+    dismissed: HashSet<String>,
+}
+
+impl Inbox {
+    /// Take the client's list, ids we haven't seen before count as received now.
+    pub fn set(&mut self, invitations: Vec<ReceivedInvitation>) {
+        let now = Local::now();
+        for invitation in &invitations {
+            self.received
+                .entry(invitation.invitation_id.clone())
+                .or_insert_with(|| invitation.sent().unwrap_or(now));
+        }
+        let ids = invitations
+            .iter()
+            .map(|invitation| invitation.invitation_id.as_str())
+            .collect::<HashSet<_>>();
+        self.received.retain(|id, _| ids.contains(id.as_str()));
+        self.dismissed.retain(|id| ids.contains(id.as_str()));
+        self.invitations = invitations;
+    }
+
+    pub fn clear(&mut self) {
+        self.invitations.clear();
+        self.received.clear();
+        self.dismissed.clear();
+    }
+
+    /// Invitations still waiting for an answer, oldest first.
+    pub fn pending(&self) -> Vec<&ReceivedInvitation> {
+        let mut pending = self
+            .invitations
+            .iter()
+            .filter(|invitation| invitation.is_pending())
+            .collect::<Vec<_>>();
+        pending.sort_by_key(|invitation| self.received(invitation));
+        pending
+    }
+
+    /// Pending invitations whose notification is still open.
+    pub fn unseen(&self) -> Vec<&ReceivedInvitation> {
+        self.pending()
+            .into_iter()
+            .filter(|invitation| !self.dismissed.contains(&invitation.invitation_id))
+            .collect()
+    }
+
+    pub fn dismiss(&mut self, invitation_id: &str) {
+        self.dismissed.insert(invitation_id.to_string());
+    }
+
+    pub fn received(&self, invitation: &ReceivedInvitation) -> Option<DateTime<Local>> {
+        self.received.get(&invitation.invitation_id).copied()
+    }
+}
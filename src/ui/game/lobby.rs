@@ -0,0 +1,60 @@
+//! `/lol-lobby/v2/lobby` as the client sends it, kassadin's `LobbyEvent` only carries the members.
+
+use kassadin::types::lcu::consts::Position;
+use kassadin::types::lcu::lobby::{Member, PositionPreference};
+use serde::Deserialize;
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct LobbySession {
+    pub game_config: LobbyGameConfig,
+    pub local_member: Option<Member>,
+    pub members: Vec<Member>,
+    /// invitations sent from this lobby, by anyone in it
+    pub invitations: Vec<LobbyInvitation>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct LobbyGameConfig {
+    pub queue_id: i64,
+    pub is_custom: bool,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct LobbyInvitation {
+    pub invitation_id: String,
+    pub to_summoner_id: i64,
+    pub to_summoner_name: String,
+    /// `Requested` until the client sent it, then `Pending`, `Accepted`, `Declined`, `Kicked` or `Error`
+    pub state: String,
+}
+
+impl LobbyInvitation {
+    /// Still waiting for an answer.
+    pub fn is_open(&self) -> bool {
+        matches!(self.state.as_str(), "Requested" | "Pending")
+    }
+}
+
+impl LobbySession {
+    /// As the client numbers queues, `None` for customs.
+    pub fn queue_id(&self) -> Option<i64> {
+        if self.game_config.is_custom {
+            return None;
+        }
+        Some(self.game_config.queue_id)
+    }
+
+    /// Our own positions, `None` if the lobby doesn't say who we are.
+    pub fn positions(&self) -> Option<PositionPreference> {
+        let local = self.local_member.as_ref()?;
+        // the client says UNSELECTED where we say nothing
+        let chosen = |position: Option<Position>| position.filter(|p| *p != Position::UNSELECTED);
+        Some(PositionPreference {
+            first_preference: chosen(local.first_position_preference),
+            second_preference: chosen(local.second_position_preference),
+        })
+    }
+}
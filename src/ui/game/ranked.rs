@@ -0,0 +1,298 @@
+//! Ranked stats by puuid, so lobby updates don't look up the same players over and over.
+//! Lookups themselves are `Command::RankedStats`, which run in parallel on the runtime.
+//!
+//! `/lol-ranked/v1/ranked-stats/{puuid}` is decoded here instead of into kassadin's `RankedStatus`,
+//! which only has fixed fields for some queues.
+
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+pub const RANKED_STATS: &str = "/lol-ranked/v1/ranked-stats";
+
+pub const SOLO: &str = "RANKED_SOLO_5x5";
+pub const FLEX: &str = "RANKED_FLEX_SR";
+pub const TFT: &str = "RANKED_TFT";
+pub const TFT_DOUBLE_UP: &str = "RANKED_TFT_DOUBLE_UP";
+pub const TFT_TURBO: &str = "RANKED_TFT_TURBO";
+
+/// stats older than this are looked up again, the old ones are shown until then
+const TTL: Duration = Duration::from_secs(10 * 60);
+/// a failed lookup is tried again after this
+const RETRY_AFTER: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct RankedStats {
+    /// by queue type, e.g. `RANKED_SOLO_5x5`
+    pub queue_map: HashMap<String, RankedQueue>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct RankedQueue {
+    pub queue_type: String,
+    /// empty or `NONE` while unranked
+    pub tier: String,
+    /// `NA` for tiers without divisions
+    pub division: String,
+    pub league_points: i64,
+    pub wins: i64,
+    pub losses: i64,
+    pub is_provisional: bool,
+    pub provisional_games_remaining: i64,
+    pub previous_season_end_tier: String,
+    pub previous_season_end_division: String,
+    /// one letter per game of the series, e.g. `WLN` after a win and a loss, empty outside of one
+    pub mini_series_progress: String,
+}
+
+impl RankedStats {
+    /// The queue to show first in a lobby of `queue_id`, as the client numbers queues.
+    /// Queues without their own rank show solo/duo, like the client does.
+    pub fn main_queue(queue_id: Option<i64>) -> &'static str {
+        match queue_id {
+            Some(440) => FLEX,
+            Some(1090) | Some(1100) => TFT,
+            Some(1130) => TFT_TURBO,
+            Some(1160) => TFT_DOUBLE_UP,
+            _ => SOLO,
+        }
+    }
+
+    pub fn queue(&self, queue_type: &str) -> Option<&RankedQueue> {
+        self.queue_map.get(queue_type)
+    }
+
+    /// Every queue but `queue_type` that is ranked or in placements, solo/duo and flex first.
+    pub fn others(&self, queue_type: &str) -> Vec<&RankedQueue> {
+        let mut others = self
+            .queue_map
+            .values()
+            .filter(|queue| queue.queue_type != queue_type)
+            .filter(|queue| queue.is_ranked() || queue.is_provisional)
+            .collect::<Vec<_>>();
+        others.sort_by_key(|queue| match queue.queue_type.as_str() {
+            SOLO => (0, String::new()),
+            FLEX => (1, String::new()),
+            other => (2, other.to_string()),
+        });
+        others
+    }
+}
+
+impl RankedQueue {
+    pub fn is_ranked(&self) -> bool {
+        !matches!(self.tier.as_str(), "" | "NONE" | "UNRANKED")
+    }
+
+    pub fn queue_name(&self) -> &str {
+        queue_name(&self.queue_type)
+    }
+
+    /// e.g. `Gold II 45 LP`, `Unranked (3 placements left)` or `Master 120 LP`.
+    pub fn rank(&self) -> String {
+        if !self.is_ranked() {
+            if self.is_provisional {
+                return format!(
+                    "Unranked ({} placements left)",
+                    self.provisional_games_remaining
+                );
+            }
+            return "Unranked".to_string();
+        }
+        format!(
+            "{} {} LP",
+            rank_name(&self.tier, &self.division),
+            self.league_points
+        )
+    }
+
+    /// e.g. `12W 10L 55%`, `None` without games.
+    pub fn record(&self) -> Option<String> {
+        let games = self.wins + self.losses;
+        if games == 0 {
+            return None;
+        }
+        Some(format!(
+            "{}W {}L {}%",
+            self.wins,
+            self.losses,
+            self.wins * 100 / games
+        ))
+    }
+
+    /// e.g. `Series W L -`, `None` outside of one.
+    pub fn series(&self) -> Option<String> {
+        if self.mini_series_progress.is_empty() {
+            return None;
+        }
+        let games = self
+            .mini_series_progress
+            .chars()
+            .map(|game| match game {
+                'W' => "W",
+                'L' => "L",
+                _ => "-",
+            })
+            .collect::<Vec<_>>();
+        Some(format!("Series {}", games.join(" ")))
+    }
+
+    /// e.g. `last season Gold II`, `None` if unranked back then.
+    pub fn previous_season(&self) -> Option<String> {
+        match self.previous_season_end_tier.as_str() {
+            "" | "NONE" | "UNRANKED" => None,
+            tier => Some(format!(
+                "last season {}",
+                rank_name(tier, &self.previous_season_end_division)
+            )),
+        }
+    }
+}
+
+pub fn queue_name(queue_type: &str) -> &str {
+    match queue_type {
+        SOLO => "Solo/Duo",
+        FLEX => "Flex",
+        TFT => "TFT",
+        TFT_DOUBLE_UP => "Double Up",
+        TFT_TURBO => "Hyper Roll",
+        other => other,
+    }
+}
+
+/// `GOLD`, `II` as `Gold II`, apex tiers come without division.
+fn rank_name(tier: &str, division: &str) -> String {
+    let mut chars = tier.chars();
+    let tier = match chars.next() {
+        Some(first) => first.to_string() + &chars.as_str().to_lowercase(),
+        None => String::new(),
+    };
+    match division {
+        "" | "NA" => tier,
+        division => format!("{} {}", tier, division),
+    }
+}
+
+#[derive(Debug, Default)]
+struct Entry {
+    ranked: Option<RankedStats>,
+    /// when `ranked` was fetched
+    fetched: Option<Instant>,
+    loading: bool,
+    failed: Option<Instant>,
+}
+
+/// What a lobby row can show for a player.
+#[derive(Debug)]
+pub enum Lookup<'a> {
+    Loaded(&'a RankedStats),
+    Loading,
+    Failed,
+}
+
+#[derive(Debug, Default)]
+pub struct RankedCache {
+    entries: HashMap<String, Entry>,
+}
+
+impl RankedCache {
+    /// Whether `puuid` needs a lookup, if so it counts as loading until `insert` or `failed`.
+    /// Lookups already running aren't started twice.
+    pub fn start_lookup(&mut self, puuid: &str, now: Instant) -> bool {
+        let entry = self.entries.entry(puuid.to_string()).or_default();
+        let expired = |at: Option<Instant>, after: Duration| {
+            at.is_none_or(|at| now.saturating_duration_since(at) >= after)
+        };
+        let due =
+            !entry.loading && expired(entry.fetched, TTL) && expired(entry.failed, RETRY_AFTER);
+        if due {
+            entry.loading = true;
+        }
+        due
+    }
+
+    pub fn insert(&mut self, puuid: &str, ranked: RankedStats, now: Instant) {
+        let entry = self.entries.entry(puuid.to_string()).or_default();
+        entry.ranked = Some(ranked);
+        entry.fetched = Some(now);
+        entry.loading = false;
+        entry.failed = None;
+    }
+
+    /// The lookup didn't work out, stats from before stay.
+    pub fn failed(&mut self, puuid: &str, now: Instant) {
+        let entry = self.entries.entry(puuid.to_string()).or_default();
+        entry.loading = false;
+        entry.failed = Some(now);
+    }
+
+    pub fn get(&self, puuid: &str) -> Option<&RankedStats> {
+        self.entries.get(puuid)?.ranked.as_ref()
+    }
+
+    pub fn lookup(&self, puuid: &str) -> Lookup<'_> {
+        match self.entries.get(puuid) {
+            Some(Entry {
+                ranked: Some(ranked),
+                ..
+            }) => Lookup::Loaded(ranked),
+            Some(Entry {
+                failed: Some(_),
+                loading: false,
+                ..
+            }) => Lookup::Failed,
+            _ => Lookup::Loading,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PUUID: &str = "puuid";
+
+    #[test]
+    fn lookups_run_once_at_a_time() {
+        let mut cache = RankedCache::default();
+        let now = Instant::now();
+        assert!(matches!(cache.lookup(PUUID), Lookup::Loading));
+        assert!(cache.start_lookup(PUUID, now));
+        assert!(!cache.start_lookup(PUUID, now + TTL));
+        assert!(matches!(cache.lookup(PUUID), Lookup::Loading));
+        // others aren't held up
+        assert!(cache.start_lookup("other", now));
+    }
+
+    #[test]
+    fn stats_expire_after_the_ttl() {
+        let mut cache = RankedCache::default();
+        let now = Instant::now();
+        assert!(cache.start_lookup(PUUID, now));
+        cache.insert(PUUID, RankedStats::default(), now);
+        assert!(matches!(cache.lookup(PUUID), Lookup::Loaded(_)));
+        assert!(!cache.start_lookup(PUUID, now + TTL - Duration::from_secs(1)));
+
+        assert!(cache.start_lookup(PUUID, now + TTL));
+        // the old stats show until the new ones are in
+        assert!(cache.get(PUUID).is_some());
+        assert!(matches!(cache.lookup(PUUID), Lookup::Loaded(_)));
+    }
+
+    #[test]
+    fn failed_lookups_are_retried_later() {
+        let mut cache = RankedCache::default();
+        let now = Instant::now();
+        assert!(cache.start_lookup(PUUID, now));
+        cache.failed(PUUID, now);
+        assert!(matches!(cache.lookup(PUUID), Lookup::Failed));
+        assert!(!cache.start_lookup(PUUID, now + RETRY_AFTER - Duration::from_secs(1)));
+
+        assert!(cache.start_lookup(PUUID, now + RETRY_AFTER));
+        assert!(matches!(cache.lookup(PUUID), Lookup::Loading));
+        cache.insert(PUUID, RankedStats::default(), now + RETRY_AFTER);
+        assert!(matches!(cache.lookup(PUUID), Lookup::Loaded(_)));
+    }
+}
@@ -0,0 +1,89 @@
+use crate::logging::{self, Entry};
+use eframe::egui;
+use log::{Level, LevelFilter};
+
+/// Window showing recent log entries, filtered by level and target.
+#[derive(Debug)]
+pub struct LogPanel {
+    pub open: bool,
+    pub level: LevelFilter,
+    /// targets that are hidden, everything else is shown
+    pub hidden: Vec<String>,
+}
+
+impl Default for LogPanel {
+    fn default() -> Self {
+        Self {
+            open: false,
+            level: LevelFilter::Info,
+            hidden: vec![],
+        }
+    }
+}
+
+impl LogPanel {
+    pub fn ui(&mut self, ctx: &egui::CtxRef) {
+        let Self {
+            open,
+            level,
+            hidden,
+        } = self;
+
+        egui::Window::new("Log")
+            .open(open)
+            .default_size(egui::Vec2::new(600.0, 400.0))
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    egui::ComboBox::from_id_source("log_level")
+                        .selected_text(level.to_string())
+                        .show_ui(ui, |ui| {
+                            for filter in LevelFilter::iter().skip(1) {
+                                ui.selectable_value(level, filter, filter.to_string());
+                            }
+                        });
+                    for target in logging::TARGETS {
+                        let mut shown = !hidden.iter().any(|hidden| hidden.as_str() == target);
+                        if ui.checkbox(&mut shown, target).changed() {
+                            if shown {
+                                hidden.retain(|hidden| hidden.as_str() != target);
+                            } else {
+                                hidden.push(target.to_string());
+                            }
+                        }
+                    }
+                });
+                ui.separator();
+
+                egui::ScrollArea::vertical().show(ui, |ui| {
+                    logging::with_entries(|entries| {
+                        for entry in entries.iter().filter(|entry| {
+                            entry.level <= *level && !hidden.contains(&entry.target)
+                        }) {
+                            ui_entry(ui, entry);
+                        }
+                    });
+                });
+            });
+    }
+}
+
+fn ui_entry(ui: &mut egui::Ui, entry: &Entry) {
+    let color = match entry.level {
+        Level::Error => crate::ui::colors::RED_A500,
+        Level::Warn => crate::ui::colors::YELLOW_A800,
+        Level::Info => egui::Color32::LIGHT_GRAY,
+        Level::Debug | Level::Trace => egui::Color32::GRAY,
+    };
+    ui.horizontal(|ui| {
+        ui.label(
+            egui::RichText::new(entry.time.format("%H:%M:%S").to_string())
+                .text_style(egui::TextStyle::Small),
+        );
+        ui.label(
+            egui::RichText::new(format!("{} [{}]", entry.level, entry.target))
+                .text_style(egui::TextStyle::Small)
+                .color(color),
+        );
+        ui.label(egui::RichText::new(&entry.message).text_style(egui::TextStyle::Small));
+    });
+}
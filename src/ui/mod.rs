@@ -1,4 +1,8 @@
 pub mod friendlist;
 pub mod account;
+pub mod chat;
 pub mod game;
 pub mod colors;
+pub mod logs;
+pub mod toasts;
+pub mod waiting;
@@ -0,0 +1,96 @@
+use crate::error::Report;
+use crate::logging;
+use chrono::{DateTime, Local};
+use eframe::egui;
+
+const MAX_HISTORY: usize = 100;
+
+#[derive(Debug)]
+pub struct Toast {
+    /// the outermost context of `report`
+    pub action: String,
+    pub report: Report,
+    pub time: DateTime<Local>,
+    pub dismissed: bool,
+}
+
+/// Failed actions, shown as toasts until dismissed and kept in a history afterwards.
+#[derive(Debug, Default)]
+pub struct Toasts {
+    pub history: Vec<Toast>,
+    pub show_history: bool,
+}
+
+impl Toasts {
+    /// Shows `report` titled with its outermost context, which should say what failed.
+    pub fn push(&mut self, report: Report) {
+        log::error!(target: logging::UI, "{:?}", report);
+
+        self.history.push(Toast {
+            action: report.to_string(),
+            report,
+            time: Local::now(),
+            dismissed: false,
+        });
+
+        if self.history.len() > MAX_HISTORY {
+            self.history.remove(0);
+        }
+    }
+
+    pub fn ui(&mut self, ctx: &egui::CtxRef) {
+        let Self {
+            history,
+            show_history,
+            ..
+        } = self;
+
+        egui::Area::new("toasts")
+            .anchor(egui::Align2::RIGHT_BOTTOM, egui::Vec2::new(-270.0, -10.0))
+            .show(ctx, |ui| {
+                for toast in history.iter_mut().filter(|t| !t.dismissed) {
+                    egui::Frame::popup(ui.style()).show(ui, |ui| {
+                        ui.set_max_width(320.0);
+                        ui.horizontal(|ui| {
+                            ui.label(
+                                egui::RichText::new(&toast.action)
+                                    .text_style(egui::TextStyle::Body)
+                                    .color(crate::ui::colors::RED_A500),
+                            );
+                            if ui.small_button("x").clicked() {
+                                toast.dismissed = true;
+                            }
+                        });
+                        ui_report(ui, &toast.report);
+                    });
+                    ui.add_space(5.0);
+                }
+            });
+
+        egui::Window::new("Errors")
+            .open(show_history)
+            .default_size(egui::Vec2::new(400.0, 300.0))
+            .show(ctx, |ui| {
+                egui::ScrollArea::vertical().show(ui, |ui| {
+                    for toast in history.iter().rev() {
+                        ui.horizontal(|ui| {
+                            ui.label(
+                                egui::RichText::new(toast.time.format("%H:%M:%S").to_string())
+                                    .text_style(egui::TextStyle::Small),
+                            );
+                            ui.label(&toast.action);
+                        });
+                        ui_report(ui, &toast.report);
+                        ui.separator();
+                    }
+                });
+            });
+    }
+}
+
+fn ui_report(ui: &mut egui::Ui, report: &Report) {
+    // the first entry is the action, which is already the title
+    for cause in report.chain().skip(1) {
+        ui.label(egui::RichText::new(cause.to_string()).text_style(egui::TextStyle::Small));
+    }
+}
@@ -0,0 +1,22 @@
+use crate::connection::ConnectionStatus;
+use eframe::egui;
+
+/// Shown instead of the lobby until a client is attached.
+/// `status` is `None` while there is no instance at all, e.g. no client process was found yet.
+pub fn ui(ui: &mut egui::Ui, status: Option<ConnectionStatus>) {
+    ui.vertical_centered(|ui| {
+        ui.add_space(ui.available_height() / 3.0);
+        ui.heading("Waiting for the League client");
+
+        let detail = match status {
+            None | Some(ConnectionStatus::WaitingForClient) | Some(ConnectionStatus::Closed) => {
+                "Start League, Clowncher attaches as soon as it is running.".to_string()
+            }
+            Some(ConnectionStatus::Failed(error)) => {
+                format!("Couldn't connect, {}. Restarting League may help.", error)
+            }
+            Some(status) => format!("{}...", status.label()),
+        };
+        ui.label(egui::RichText::new(detail).color(crate::ui::colors::GRAY_A500));
+    });
+}
@@ -0,0 +1,221 @@
+//! Commands and the socket against `clowncher::mock`, the way the app drives a real client.
+
+use clowncher::command::{self, Command, CommandOutput, CommandQueue};
+use clowncher::connection::{self, ConnectionStatus, Credentials};
+use clowncher::event::Event;
+use clowncher::mock::{self, MockClient, MockConfig, MockScript};
+use clowncher::router::Router;
+use crossbeam::channel::{Receiver, RecvTimeoutError};
+use kassadin::routes;
+use kassadin::types::lcu::lobby::PositionPreference;
+use kassadin::types::socket::EventType;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+const TIMEOUT: Duration = Duration::from_secs(10);
+const CONVERSATIONS: &str = "/lol-chat/v1/conversations";
+const INVITATIONS: &str = "/lol-lobby/v2/received-invitations";
+
+/// A mock that only changes when a test makes it.
+fn spawn() -> MockClient {
+    mock::spawn(MockConfig {
+        script: MockScript {
+            friend_flip_interval: 0,
+            invite_interval: 0,
+            message_interval: 0,
+            ..Default::default()
+        },
+        ..Default::default()
+    })
+}
+
+fn join_lobby(queue_id: i64) -> Command {
+    Command::JoinLobby(
+        queue_id,
+        PositionPreference {
+            first_preference: None,
+            second_preference: None,
+        },
+    )
+}
+
+/// Skip events until `f` picks one, panics with `what` if none comes in time.
+fn expect<T>(events: &Receiver<Event>, what: &str, mut f: impl FnMut(Event) -> Option<T>) -> T {
+    let deadline = Instant::now() + TIMEOUT;
+    loop {
+        let left = deadline.saturating_duration_since(Instant::now());
+        match events.recv_timeout(left) {
+            Ok(event) => {
+                if let Some(found) = f(event) {
+                    return found;
+                }
+            }
+            Err(RecvTimeoutError::Timeout) => panic!("timed out waiting for {}", what),
+            Err(RecvTimeoutError::Disconnected) => panic!("disconnected waiting for {}", what),
+        }
+    }
+}
+
+/// Send `command` and wait for its result.
+fn run(
+    queue: &mut CommandQueue,
+    events: &Receiver<Event>,
+    command: Command,
+) -> Result<CommandOutput, String> {
+    let sent = queue.send(command);
+    let result = expect(events, "the command", |event| match event {
+        Event::CommandDone(0, id, _, result) if id == sent => Some(result),
+        _ => None,
+    });
+    queue.finish(sent);
+    result.map_err(|e| format!("{:#}", e))
+}
+
+#[test]
+fn commands_change_the_mock() {
+    let mock = spawn();
+    let (send, events) = crossbeam::channel::unbounded();
+    let mut queue = CommandQueue::new(0, mock.lcu(), send);
+
+    assert!(run(&mut queue, &events, join_lobby(420)).is_ok());
+    assert_eq!(mock.with_state(|state| state.queue_id), Some(420));
+    assert!(run(&mut queue, &events, Command::StartQueue).is_ok());
+    assert_eq!(mock.with_state(|state| state.phase), "Matchmaking");
+    assert!(!queue.is_pending(|_| true));
+
+    assert!(run(&mut queue, &events, Command::StopQueue).is_ok());
+    assert!(run(&mut queue, &events, Command::Invite(2)).is_ok());
+    let invited = mock.with_state(|state| state.sent_invitations.len());
+    assert_eq!(invited, 1);
+    assert!(run(&mut queue, &events, Command::LeaveLobby).is_ok());
+    assert_eq!(mock.with_state(|state| state.phase), "None");
+}
+
+#[test]
+fn commands_decode_what_the_mock_answers() {
+    let mock = spawn();
+    let (send, events) = crossbeam::channel::unbounded();
+    let mut queue = CommandQueue::new(0, mock.lcu(), send);
+
+    let puuid = "mock-puuid-2".to_string();
+    match run(&mut queue, &events, Command::RankedStats(puuid.clone())) {
+        Ok(CommandOutput::RankedStats(of, _)) => assert_eq!(of, puuid),
+        Err(e) => panic!("no ranked stats: {}", e),
+        Ok(_) => panic!("no ranked stats"),
+    }
+    match run(&mut queue, &events, Command::LoadFriends) {
+        Ok(CommandOutput::Friends(friends)) => assert_eq!(friends.len(), 4),
+        Err(e) => panic!("no friends: {}", e),
+        Ok(_) => panic!("no friends"),
+    }
+    match run(&mut queue, &events, Command::LoadQueues) {
+        Ok(CommandOutput::Queues(queues, eligibility)) => {
+            assert!(!queues.is_empty());
+            assert!(!eligibility.is_empty());
+        }
+        Err(e) => panic!("no queues: {}", e),
+        Ok(_) => panic!("no queues"),
+    }
+
+    let id = "mock-puuid-2@eu1.pvp.net".to_string();
+    assert!(run(&mut queue, &events, Command::OpenConversation(id.clone())).is_ok());
+    let message = Command::SendMessage(id.clone(), "chat", "hi".to_string());
+    assert!(run(&mut queue, &events, message).is_ok());
+    match run(&mut queue, &events, Command::LoadConversation(id.clone())) {
+        Ok(CommandOutput::Conversation(of, messages, _)) => {
+            assert_eq!(of, id);
+            // the mock answers direct messages
+            assert_eq!(messages.len(), 2);
+        }
+        Err(e) => panic!("no conversation: {}", e),
+        Ok(_) => panic!("no conversation"),
+    }
+}
+
+#[test]
+fn failed_commands_say_what_was_tried() {
+    let mock = spawn();
+    // no ready check to accept
+    let error = match command::run_blocking(&mock.lcu(), &Command::Accept) {
+        Err(e) => format!("{:#}", e),
+        Ok(_) => panic!("accepted without a ready check"),
+    };
+    assert!(error.starts_with("Accept match"), "{}", error);
+    assert!(error.contains("409"), "{}", error);
+}
+
+#[test]
+fn wrong_password_is_unauthorized() {
+    let mut mock = spawn();
+    mock.password = "wrong".to_string();
+    let error = match command::run_blocking(&mock.lcu(), &Command::LoadFriends) {
+        Err(e) => format!("{:#}", e),
+        Ok(_) => panic!("loaded friends with the wrong password"),
+    };
+    assert!(error.contains("401"), "{}", error);
+}
+
+#[test]
+fn socket_hydrates_then_follows_the_mock() {
+    let mock = spawn();
+    let (send, events) = crossbeam::channel::unbounded();
+    let stop = Arc::new(AtomicBool::new(false));
+    let credentials = Credentials::Fixed(mock.lcu());
+    let router = Arc::new(Router::league());
+    let connection_stop = stop.clone();
+    let connection = std::thread::spawn(move || {
+        connection::run(0, credentials, router, None, send, connection_stop)
+    });
+
+    expect(&events, "connected", |event| match event {
+        Event::Connection(0, ConnectionStatus::Connected) => Some(()),
+        _ => None,
+    });
+    let lcu = expect(&events, "reconnected", |event| match event {
+        Event::Reconnected(0, lcu) => Some(lcu),
+        _ => None,
+    });
+    assert_eq!(lcu.info.unwrap().token, mock.lcu().info.unwrap().token);
+
+    // gameflow is hydrated first and conversations last, outside a lobby the rest is missing
+    let mut hydrated = vec![];
+    expect(&events, "hydration", |event| match event {
+        Event::LeagueEvent(0, event) => {
+            assert!(event.error.is_none(), "{:?}", event.error);
+            hydrated.push(event.uri.clone());
+            (event.uri == CONVERSATIONS).then_some(())
+        }
+        _ => None,
+    });
+    assert_eq!(
+        hydrated,
+        [routes::game_flow::SESSION, INVITATIONS, CONVERSATIONS]
+    );
+
+    // live events from a request that went through the mock's rest api
+    command::run_blocking(&mock.lcu(), &join_lobby(400)).unwrap();
+    let event_type = expect(&events, "the lobby", |event| match event {
+        Event::LeagueEvent(0, event) if event.uri == routes::lobby::LOBBY => {
+            assert!(event.error.is_none(), "{:?}", event.error);
+            Some(event.event_type)
+        }
+        _ => None,
+    });
+    assert!(matches!(event_type, Some(EventType::Create)));
+
+    // and from the script
+    mock.with_state(|state| state.receive_invitation(0));
+    expect(&events, "the invitation", |event| match event {
+        Event::LeagueEvent(0, event) if event.uri == INVITATIONS => Some(()),
+        _ => None,
+    });
+
+    // without another frame to wake it up
+    stop.store(true, Ordering::Relaxed);
+    expect(&events, "closed", |event| match event {
+        Event::Connection(0, ConnectionStatus::Closed) => Some(()),
+        _ => None,
+    });
+    connection.join().unwrap();
+}